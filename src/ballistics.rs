@@ -0,0 +1,92 @@
+use cartridge::CartridgeSpec;
+use collision::*;
+use config::Config;
+use gunship::math::*;
+use physics::GRAVITY;
+
+/// The density of air at sea level in kilograms per cubic meter.
+pub const AIR_DENSITY: f32 = 1.225;
+
+/// The longest step (in seconds) a bullet's flight is integrated over.
+///
+/// Bullets move several meters per frame, so integrating once per frame would noticeably
+/// underestimate drop and drag at long range.
+pub const SUBSTEP: f32 = 1.0 / 1000.0;
+
+/// The air that bullets fly through.
+#[derive(Debug, Clone, Copy)]
+pub struct Atmosphere {
+    /// Air density in kilograms per cubic meter.
+    pub air_density: f32,
+
+    /// The velocity of the wind in meters per second. The wind is the same everywhere.
+    pub wind: Vector3,
+}
+
+impl Atmosphere {
+    /// Applies any atmosphere settings found in `config`, leaving the others unchanged.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.air_density = config.get_or("air_density", self.air_density);
+        self.wind.x = config.get_or("wind_x", self.wind.x);
+        self.wind.y = config.get_or("wind_y", self.wind.y);
+        self.wind.z = config.get_or("wind_z", self.wind.z);
+    }
+}
+
+impl Default for Atmosphere {
+    /// Still air at sea level.
+    fn default() -> Atmosphere {
+        Atmosphere {
+            air_density: AIR_DENSITY,
+            wind: Vector3::zero(),
+        }
+    }
+}
+
+/// Calculates the acceleration on a bullet of type `spec` flying at `velocity` from gravity and
+/// air drag.
+pub fn acceleration(spec: &CartridgeSpec, atmosphere: &Atmosphere, velocity: Vector3) -> Vector3 {
+    let gravity = Vector3::new(0.0, -GRAVITY, 0.0);
+
+    // Drag depends on how fast the bullet is moving through the air, so wind pushes the bullet
+    // along with it.
+    let air_velocity = velocity - atmosphere.wind;
+    let speed = air_velocity.magnitude();
+    if speed <= 0.0 {
+        return gravity;
+    }
+
+    let drag = 0.5 * atmosphere.air_density * speed * speed / spec.ballistic_coefficient();
+    gravity - air_velocity / speed * drag
+}
+
+/// Describes a bullet striking a collider.
+#[derive(Debug, Clone, Copy)]
+pub struct Hit {
+    pub target: ColliderId,
+    pub point: Point,
+    pub normal: Vector3,
+
+    /// The total distance the bullet travelled before the impact, in meters.
+    pub distance: f32,
+}
+
+/// Sweeps the segment a bullet travelled over a single frame against the colliders in `world`.
+///
+/// `travelled` is the distance the bullet had covered before the start of the segment, and is
+/// used to report the total distance travelled for the hit. Only colliders on one of the layers
+/// in `mask` are hit.
+pub fn sweep(world: &CollisionWorld, start: Point, end: Point, travelled: f32, mask: u32) -> Option<Hit> {
+    let segment = end - start;
+    let length = segment.magnitude();
+    if length <= 0.0 {
+        return None;
+    }
+
+    world.raycast(start, segment / length, length, mask).map(|hit| Hit {
+        target: hit.collider,
+        point: hit.point,
+        normal: hit.normal,
+        distance: travelled + hit.distance,
+    })
+}
@@ -0,0 +1,502 @@
+use bore::{Bore, Firing};
+use bullet::BulletPool;
+use cartridge::CartridgeSpec;
+use collision::Shape;
+use gun::Cartridge;
+use gunship::*;
+use gunship::math::*;
+use gunship::mesh_renderer::MeshRenderer;
+use gunship::resource::Mesh;
+use gunship::transform::Transform;
+use magazine::Magazine;
+use physics::{InertiaTensor, Integrator, Rigidbody};
+use recoil::Recoil;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use tween;
+use weapon::{ActionEvent, Weapon};
+
+/// The angle (in radians) the bolt handle rotates up when the bolt is unlocked.
+const BOLT_LIFT_ANGLE: f32 = 1.4;
+
+/// How far (in meters) the bolt slides back when pulled.
+const BOLT_TRAVEL: f32 = 0.09;
+
+/// How quickly the bolt moves between positions. The remaining distance decays exponentially at
+/// this rate, so higher values are snappier.
+const BOLT_RATE: f32 = 30.0;
+
+/// How long (in seconds) it takes to bring the scope up to the player's eye.
+const AIM_TIME: f32 = 0.2;
+
+/// The speed (in meters per second) at which the ejector flicks cartridges out of the action.
+const EJECT_SPEED: f32 = 2.0;
+
+/// The position of a bolt-action's bolt.
+///
+/// Working the bolt steps through each position in order: lift, pull, push, lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoltState {
+    /// The bolt is forward with the handle down, locking the action closed. The rifle can fire.
+    Locked,
+
+    /// The handle has been lifted, which cocks the striker.
+    Unlocked,
+
+    /// The bolt has been pulled all the way back, extracting and ejecting whatever was in the
+    /// chamber. The action is open and can be loaded.
+    Back,
+
+    /// The bolt has been pushed forward, stripping the top round off the magazine into the
+    /// chamber. The handle is still up.
+    Forward,
+}
+
+impl BoltState {
+    /// Gets the bolt's handle angle and how far back it is.
+    fn position(self) -> (f32, f32) {
+        match self {
+            BoltState::Locked => (0.0, 0.0),
+            BoltState::Unlocked => (BOLT_LIFT_ANGLE, 0.0),
+            BoltState::Back => (BOLT_LIFT_ANGLE, BOLT_TRAVEL),
+            BoltState::Forward => (BOLT_LIFT_ANGLE, 0.0),
+        }
+    }
+}
+
+/// A bolt-action rifle with an internal magazine and a scope.
+///
+/// The magazine is loaded from the top with the bolt back, either a single round at a time or a
+/// full stripper clip at once.
+#[derive(Debug)]
+pub struct BoltRifle {
+    pub transform: Transform,
+    pub mesh_renderer: MeshRenderer,
+    pub rigidbody: Rigidbody,
+    pub recoil: Recoil,
+
+    /// The chamberings of the cartridges the gun can be loaded with.
+    pub chamberings: Vec<String>,
+
+    pub bolt_transform: Transform,
+    pub bolt_renderer: MeshRenderer,
+
+    /// How much the scope narrows the player's field of view.
+    pub scope_magnification: f32,
+
+    magazine: Magazine,
+    chamber: Option<Cartridge>,
+    bolt: BoltState,
+    striker_cocked: bool,
+
+    /// How far the scope has been brought up to the player's eye, from 0 at the hip to 1 when
+    /// fully aimed.
+    aim: f32,
+
+    bolt_angle: f32,
+    bolt_position: f32,
+    bolt_offset: Vector3,
+    chamber_offset: Vector3,
+    magazine_offset: Vector3,
+
+    pub bore: Bore,
+
+    events: Vec<ActionEvent>,
+    ejected: Vec<Cartridge>,
+
+    bullets: Arc<Mutex<BulletPool>>,
+}
+
+impl BoltRifle {
+    pub fn new(
+        mesh: &Mesh,
+        bolt_mesh: &Mesh,
+        bullets: Arc<Mutex<BulletPool>>,
+        magazine_capacity: usize,
+        start_pos: Point,
+        start_orientation: Orientation,
+    ) -> BoltRifle {
+        let mut transform = Transform::new();
+        transform.set_position(start_pos);
+        transform.set_orientation(start_orientation);
+        let mesh_renderer = MeshRenderer::new(&mesh, &transform);
+        let mut rigidbody = Rigidbody::new();
+        rigidbody.integrator = Integrator::VelocityVerlet;
+
+        // Approximate the rifle as a long solid box.
+        rigidbody.mass = 4.0;
+        rigidbody.inertia = InertiaTensor::from_shape(
+            &Shape::Obb { half_extents: Vector3::new(0.025, 0.06, 0.55) },
+            rigidbody.mass,
+        );
+        rigidbody.teleport(start_pos, start_orientation);
+
+        let mut bolt_transform = Transform::new();
+        bolt_transform.set_position(start_pos);
+        bolt_transform.set_scale(Vector3::new(0.06, 0.01, 0.01));
+        let bolt_renderer = MeshRenderer::new(&bolt_mesh, &bolt_transform);
+
+        BoltRifle {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+            rigidbody: rigidbody,
+            recoil: Recoil {
+                bore_offset: Vector3::new(0.0, 0.03, -0.55),
+                .. Recoil::default()
+            },
+
+            chamberings: vec!["303_british".into()],
+
+            bolt_transform: bolt_transform,
+            bolt_renderer: bolt_renderer,
+
+            scope_magnification: 4.0,
+
+            magazine: Magazine::new(magazine_capacity),
+            chamber: None,
+            bolt: BoltState::Locked,
+            striker_cocked: false,
+
+            aim: 0.0,
+
+            bolt_angle: 0.0,
+            bolt_position: 0.0,
+            bolt_offset: Vector3::new(0.03, 0.03, 0.05),
+            chamber_offset: Vector3::new(0.0, 0.03, -0.02),
+            magazine_offset: Vector3::new(0.0, 0.0, 0.0),
+
+            bore: Bore::new(Vector3::new(0.0, 0.03, -1.1)),
+
+            events: Vec::new(),
+            ejected: Vec::new(),
+
+            bullets: bullets,
+        }
+    }
+
+    /// Gets the current position of the bolt.
+    pub fn bolt(&self) -> BoltState {
+        self.bolt
+    }
+
+    pub fn magazine(&self) -> &Magazine {
+        &self.magazine
+    }
+
+    pub fn chamber(&self) -> &Option<Cartridge> {
+        &self.chamber
+    }
+
+    /// Takes all action events that have happened since the last call.
+    pub fn drain_events(&mut self) -> Vec<ActionEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Takes every cartridge the bolt has thrown out since the last call.
+    pub fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        mem::replace(&mut self.ejected, Vec::new())
+    }
+
+    /// Pulls the trigger, releasing the striker if the bolt is locked.
+    pub fn pull_trigger(&mut self) {
+        // Striking again during a hang-fire would lose track of the round that's about to go off.
+        if self.bolt != BoltState::Locked || self.bore.is_hanging_fire() {
+            self.events.push(ActionEvent::TriggerBlocked);
+            return;
+        }
+
+        if !self.striker_cocked {
+            return;
+        }
+        self.striker_cocked = false;
+
+        let event = match self.chamber.as_mut() {
+            Some(cartridge) => {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                self.bore.strike(cartridge, &mut firing)
+            },
+            None => ActionEvent::DryFired,
+        };
+        self.events.push(event);
+    }
+
+    /// Counts down a pending hang-fire, firing the cartridge once it goes off.
+    pub fn update(&mut self, delta: f32) {
+        if !self.bore.update(delta) {
+            return;
+        }
+
+        if let Some(cartridge) = self.chamber.as_mut() {
+            let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+            let event = self.bore.discharge(cartridge, &mut firing);
+            self.events.push(event);
+        }
+    }
+
+    /// Pushes a rod down the barrel, knocking out a stuck bullet.
+    ///
+    /// The barrel can only be reached with the bolt back.
+    pub fn clear_bore(&mut self) {
+        if self.bolt == BoltState::Back && self.bore.clear() {
+            self.events.push(ActionEvent::BoreCleared);
+        }
+    }
+
+    /// Moves the bolt on to the next step: lift, pull, push, then lock.
+    pub fn work_bolt(&mut self) {
+        match self.bolt {
+            BoltState::Locked => self.lift_bolt(),
+            BoltState::Unlocked => self.pull_bolt(),
+            BoltState::Back => self.push_bolt(),
+            BoltState::Forward => self.lock_bolt(),
+        }
+    }
+
+    /// Lifts the bolt handle, unlocking the action and cocking the striker.
+    pub fn lift_bolt(&mut self) {
+        if self.bolt != BoltState::Locked {
+            return;
+        }
+
+        self.bolt = BoltState::Unlocked;
+        if !self.striker_cocked {
+            self.striker_cocked = true;
+            self.events.push(ActionEvent::Cocked);
+        }
+    }
+
+    /// Pulls the bolt back, extracting and ejecting whatever's in the chamber.
+    pub fn pull_bolt(&mut self) {
+        match self.bolt {
+            BoltState::Unlocked | BoltState::Forward => {},
+            _ => return,
+        }
+
+        self.bolt = BoltState::Back;
+        self.events.push(ActionEvent::ActionOpened);
+
+        if let Some(mut cartridge) = self.chamber.take() {
+            self.bore.extract(&mut cartridge);
+            self.ejected.push(cartridge);
+            self.events.push(ActionEvent::CartridgesEjected(1));
+        }
+    }
+
+    /// Pushes the bolt forward, stripping the top round off the magazine into the chamber.
+    pub fn push_bolt(&mut self) {
+        if self.bolt != BoltState::Back {
+            return;
+        }
+
+        self.bolt = BoltState::Forward;
+        if self.chamber.is_none() {
+            self.chamber = self.magazine.pop();
+        }
+    }
+
+    /// Turns the bolt handle down, locking the action.
+    pub fn lock_bolt(&mut self) {
+        match self.bolt {
+            BoltState::Unlocked | BoltState::Forward => {},
+            _ => return,
+        }
+
+        self.bolt = BoltState::Locked;
+        self.events.push(ActionEvent::ActionClosed);
+    }
+
+    /// Presses a single cartridge into the magazine while the bolt is back.
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if !cartridge.spec.fits(&self.chamberings) {
+            return Err(cartridge);
+        }
+
+        if self.bolt != BoltState::Back {
+            return Err(cartridge);
+        }
+
+        // TODO: Animate loading the cartridge.
+        self.magazine.push(cartridge)
+    }
+
+    /// Strips a whole clip of cartridges into the magazine while the bolt is back.
+    ///
+    /// Returns any cartridges that didn't fit, which stay in the clip.
+    pub fn load_clip(&mut self, cartridges: Vec<Cartridge>) -> Vec<Cartridge> {
+        let mut remaining = Vec::new();
+        for cartridge in cartridges {
+            if let Err(cartridge) = self.load_cartridge(cartridge) {
+                remaining.push(cartridge);
+            }
+        }
+
+        remaining
+    }
+
+    /// Brings the scope up to the player's eye, or lowers it again.
+    ///
+    /// Call this every frame with whether or not the player is aiming.
+    pub fn raise_scope(&mut self, aiming: bool) {
+        let step = time::delta_f32() / AIM_TIME;
+        let aim = if aiming { self.aim + step } else { self.aim - step };
+        self.aim = aim.clamp(0.0, 1.0);
+    }
+
+    /// Gets how much the scope is currently magnifying the player's view.
+    pub fn magnification(&self) -> f32 {
+        1.0 + (self.scope_magnification - 1.0) * tween::ease_out_quad(self.aim)
+    }
+
+    /// Gets the speed and direction cartridges are thrown when ejected, in world space.
+    pub fn eject_velocity(&self) -> Vector3 {
+        // The ejector flicks cartridges out of the right side of the action.
+        let direction = Vector3::new(1.0, 0.6, 0.2).normalized();
+        self.rigidbody.velocity() + self.transform.orientation() * (direction * EJECT_SPEED)
+    }
+
+    pub fn update_transforms(&mut self) {
+        // Ease the bolt towards wherever its current step puts it.
+        let (target_angle, target_position) = self.bolt.position();
+        let fraction = 1.0 - (-BOLT_RATE * time::delta_f32()).exp();
+        self.bolt_angle += (target_angle - self.bolt_angle) * fraction;
+        self.bolt_position += (target_position - self.bolt_position) * fraction;
+
+        let orientation = self.transform.orientation();
+        let position = self.transform.position();
+
+        // The handle sticks out to the right of the bolt, and rotates around the bore.
+        let bolt_rotation = Orientation::from_eulers(0.0, 0.0, self.bolt_angle);
+        let bolt_offset = bolt_rotation * self.bolt_offset + Vector3::new(0.0, 0.0, self.bolt_position);
+        self.bolt_transform.set_position(position + orientation * bolt_offset);
+        self.bolt_transform.set_orientation(orientation + bolt_rotation);
+
+        if let Some(cartridge) = self.chamber.as_mut() {
+            cartridge.transform.set_position(position + orientation * self.chamber_offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+
+        // Cartridges stack in two staggered columns in the magazine.
+        for (index, cartridge) in self.magazine.iter_mut().rev().enumerate() {
+            let stagger = if index % 2 == 0 { 0.004 } else { -0.004 };
+            let offset = self.magazine_offset + Vector3::new(stagger, -0.008 * index as f32, 0.0);
+            cartridge.transform.set_position(position + orientation * offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+    }
+}
+
+impl Weapon for BoltRifle {
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn rigidbody(&self) -> &Rigidbody {
+        &self.rigidbody
+    }
+
+    fn rigidbody_mut(&mut self) -> &mut Rigidbody {
+        &mut self.rigidbody
+    }
+
+    fn recoil(&self) -> &Recoil {
+        &self.recoil
+    }
+
+    fn pull_trigger(&mut self) {
+        BoltRifle::pull_trigger(self);
+    }
+
+    /// Aims through the scope for as long as the button is held.
+    fn secondary_action(&mut self, held: bool) {
+        self.raise_scope(held);
+    }
+
+    fn is_action_open(&self) -> bool {
+        self.bolt == BoltState::Back
+    }
+
+    /// Lifts and pulls the bolt all the way back.
+    fn open_action(&mut self) {
+        self.lift_bolt();
+        self.pull_bolt();
+    }
+
+    /// Pushes the bolt forward and locks it.
+    fn close_action(&mut self) {
+        self.push_bolt();
+        self.lock_bolt();
+    }
+
+    /// Works the bolt one step per unit of `amount`.
+    fn cycle(&mut self, amount: isize) {
+        for _ in 0..amount.abs() {
+            self.work_bolt();
+        }
+    }
+
+    fn accepts(&self, spec: &CartridgeSpec) -> bool {
+        spec.fits(&self.chamberings)
+    }
+
+    fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        BoltRifle::load_cartridge(self, cartridge)
+    }
+
+    fn load_clip(&mut self, cartridges: Vec<Cartridge>) -> Vec<Cartridge> {
+        BoltRifle::load_clip(self, cartridges)
+    }
+
+    /// Drops the magazine's floorplate while the bolt is back, dumping every cartridge.
+    fn unload(&mut self) -> Vec<Cartridge> {
+        if self.bolt != BoltState::Back {
+            return Vec::new();
+        }
+
+        self.magazine.take_all()
+    }
+
+    /// Thumbs the top cartridge out of the magazine while the bolt is back.
+    fn unload_one(&mut self) -> Option<Cartridge> {
+        if self.bolt != BoltState::Back {
+            return None;
+        }
+
+        self.magazine.pop()
+    }
+
+    fn eject_velocity(&self) -> Vector3 {
+        BoltRifle::eject_velocity(self)
+    }
+
+    fn clear_bore(&mut self) {
+        BoltRifle::clear_bore(self);
+    }
+
+    fn update(&mut self, delta: f32) {
+        BoltRifle::update(self, delta);
+    }
+
+    fn drain_events(&mut self) -> Vec<ActionEvent> {
+        BoltRifle::drain_events(self)
+    }
+
+    fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        BoltRifle::drain_ejected(self)
+    }
+
+    fn aim(&self) -> f32 {
+        self.aim
+    }
+
+    fn magnification(&self) -> f32 {
+        BoltRifle::magnification(self)
+    }
+
+    fn interpolate(&mut self, alpha: f32) {
+        self.rigidbody.interpolate(&mut self.transform, alpha);
+    }
+
+    fn update_transforms(&mut self) {
+        BoltRifle::update_transforms(self);
+    }
+}
@@ -0,0 +1,454 @@
+use bullet::BulletPool;
+use cartridge::{CartridgeSpec, Ignition};
+use gun::Cartridge;
+use gunship::math::*;
+use gunship::transform::Transform;
+use physics::Rigidbody;
+use random::Random;
+use recoil::Recoil;
+use recoil::Load;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use weapon::ActionEvent;
+
+/// Something that can be chambered and struck, i.e. a `Cartridge`.
+pub trait Round {
+    fn spec(&self) -> &Arc<CartridgeSpec>;
+
+    fn has_fired(&self) -> bool;
+
+    /// Marks the round as spent.
+    fn fire(&mut self);
+}
+
+impl Round for Cartridge {
+    fn spec(&self) -> &Arc<CartridgeSpec> {
+        &self.spec
+    }
+
+    fn has_fired(&self) -> bool {
+        Cartridge::has_fired(self)
+    }
+
+    fn fire(&mut self) {
+        Cartridge::fire(self);
+    }
+}
+
+/// The gun a bore belongs to, which bullets leave from and which takes the recoil.
+pub trait Muzzle {
+    /// Sends a bullet of type `spec` out of the gun from `offset` (relative to the gun), turned
+    /// by `spread` away from where the gun is aimed.
+    fn spawn(&mut self, spec: &Arc<CartridgeSpec>, offset: Vector3, spread: Orientation);
+
+    /// Kicks the gun back from firing `load`.
+    fn recoil(&mut self, load: &Load);
+}
+
+/// A gun being fired, described by its `transform`, `rigidbody`, and `recoil`. Bullets are
+/// spawned in `bullets`.
+pub struct Firing<'a> {
+    pub bullets: &'a Mutex<BulletPool>,
+    pub transform: &'a Transform,
+    pub rigidbody: &'a mut Rigidbody,
+    pub recoil: &'a Recoil,
+}
+
+impl<'a> Firing<'a> {
+    pub fn new(
+        bullets: &'a Mutex<BulletPool>,
+        transform: &'a Transform,
+        rigidbody: &'a mut Rigidbody,
+        recoil: &'a Recoil,
+    ) -> Firing<'a> {
+        Firing {
+            bullets: bullets,
+            transform: transform,
+            rigidbody: rigidbody,
+            recoil: recoil,
+        }
+    }
+}
+
+impl<'a> Muzzle for Firing<'a> {
+    fn spawn(&mut self, spec: &Arc<CartridgeSpec>, offset: Vector3, spread: Orientation) {
+        let position = self.transform.position() + self.transform.orientation() * offset;
+        let orientation = self.transform.orientation() + spread;
+        let velocity = self.rigidbody.velocity();
+        self.bullets.lock().unwrap().spawn(spec.clone(), position, orientation, velocity);
+    }
+
+    fn recoil(&mut self, load: &Load) {
+        self.recoil.apply(load, self.rigidbody);
+    }
+}
+
+/// A gun's bore, which the bullet travels down from the chamber to the muzzle.
+///
+/// The bore decides what happens once a cartridge's primer is struck. Usually the bullet is sent
+/// out of the muzzle, but unreliable cartridges can misfire, hang-fire, or squib. A squib leaves
+/// its bullet stuck in the bore, and nothing else can get out until it's cleared.
+#[derive(Debug)]
+pub struct Bore {
+    /// The muzzle's position relative to the gun.
+    pub muzzle_offset: Vector3,
+
+    /// The half-angle (in radians) of the cone that pellets spread over as they leave the muzzle.
+    pub spread: f32,
+
+    obstructed: bool,
+
+    /// The time (in seconds) until a hang-fire goes off.
+    hang_fire: Option<f32>,
+
+    random: Random,
+}
+
+impl Bore {
+    pub fn new(muzzle_offset: Vector3) -> Bore {
+        Bore {
+            muzzle_offset: muzzle_offset,
+            spread: 0.0,
+
+            obstructed: false,
+            hang_fire: None,
+
+            random: Random::from_time(),
+        }
+    }
+
+    /// Reseeds the random number generator that decides whether cartridges go off, so that the
+    /// same sequence of shots always has the same outcome.
+    pub fn seed(&mut self, seed: u32) {
+        self.random = Random::new(seed);
+    }
+
+    /// Whether a squib has left a bullet stuck in the bore.
+    pub fn is_obstructed(&self) -> bool {
+        self.obstructed
+    }
+
+    /// Whether a cartridge has been struck and is waiting to go off.
+    pub fn is_hanging_fire(&self) -> bool {
+        self.hang_fire.is_some()
+    }
+
+    /// Knocks a stuck bullet out of the bore.
+    ///
+    /// Returns `true` if there was anything to clear.
+    pub fn clear(&mut self) -> bool {
+        mem::replace(&mut self.obstructed, false)
+    }
+
+    /// Rolls what happens when the primer of a cartridge of type `spec` is struck, and updates
+    /// the bore to match.
+    ///
+    /// A hang-fire starts counting down in `update()`, and a squib obstructs the bore.
+    pub fn ignite(&mut self, spec: &CartridgeSpec) -> Ignition {
+        let ignition = spec.ignite(&mut self.random);
+        match ignition {
+            Ignition::HangFire(delay) => self.hang_fire = Some(delay),
+            Ignition::Squib => self.obstructed = true,
+            Ignition::Fire | Ignition::Misfire => {},
+        }
+
+        ignition
+    }
+
+    /// Strikes the primer of `round`, which is chambered in the gun behind `muzzle`.
+    ///
+    /// Returns an event describing what happened. If the round hang-fires, `update()` reports
+    /// when it goes off.
+    pub fn strike<R: Round, M: Muzzle>(&mut self, round: &mut R, muzzle: &mut M) -> ActionEvent {
+        if round.has_fired() {
+            return ActionEvent::DryFired;
+        }
+
+        let spec = round.spec().clone();
+        match self.ignite(&spec) {
+            Ignition::Fire => self.discharge(round, muzzle),
+
+            Ignition::Misfire => ActionEvent::Misfired,
+
+            Ignition::HangFire(_) => ActionEvent::HangFire,
+
+            Ignition::Squib => {
+                round.fire();
+                ActionEvent::Squibbed
+            },
+        }
+    }
+
+    /// Counts down a pending hang-fire.
+    ///
+    /// Returns `true` once the hang-fire goes off, at which point the gun should `discharge()`
+    /// the cartridge if it's still chambered.
+    pub fn update(&mut self, delta: f32) -> bool {
+        match self.hang_fire {
+            Some(remaining) if remaining > delta => {
+                self.hang_fire = Some(remaining - delta);
+                false
+            },
+            Some(_) => {
+                self.hang_fire = None;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Cancels a pending hang-fire.
+    ///
+    /// Returns `true` if there was one to cancel.
+    pub fn defuse(&mut self) -> bool {
+        self.hang_fire.take().is_some()
+    }
+
+    /// Pulls `round` out of the chamber.
+    ///
+    /// If it was hanging fire it goes off harmlessly outside the gun instead of being fired.
+    pub fn extract<R: Round>(&mut self, round: &mut R) {
+        if self.defuse() {
+            round.fire();
+        }
+    }
+
+    /// Fires `round`, sending its bullets out of `muzzle` and applying recoil to the gun.
+    pub fn discharge<R: Round, M: Muzzle>(&mut self, round: &mut R, muzzle: &mut M) -> ActionEvent {
+        if round.has_fired() {
+            return ActionEvent::DryFired;
+        }
+
+        // TODO: Play audio on gunshot.
+        round.fire();
+
+        // The stuck bullet soaks up the shot.
+        if self.obstructed {
+            return ActionEvent::BoreObstructed;
+        }
+
+        let spec = round.spec();
+        for _ in 0..spec.pellets {
+            // Spread the pellets evenly over the cone. Taking the square root of the random
+            // radius keeps them from bunching up in the middle.
+            let mut spread = Orientation::default();
+            if self.spread > 0.0 {
+                let radius = self.spread * self.random.next_f32().sqrt();
+                let angle = self.random.range(0.0, TAU);
+                spread = Orientation::from_eulers(radius * angle.sin(), radius * angle.cos(), 0.0);
+            }
+
+            muzzle.spawn(spec, self.muzzle_offset, spread);
+        }
+
+        muzzle.recoil(&spec.load);
+        ActionEvent::Fired(spec.load)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gunship::math::*;
+    use super::*;
+
+    /// Asserts that `event` matches `pattern`.
+    macro_rules! assert_event {
+        ($event:expr, $pattern:pat) => {
+            match $event {
+                $pattern => {},
+                event => panic!("expected {}, got {:?}", stringify!($pattern), event),
+            }
+        };
+    }
+
+    /// A round that only tracks whether it's been fired.
+    struct TestRound {
+        spec: Arc<CartridgeSpec>,
+        fired: bool,
+    }
+
+    impl TestRound {
+        fn new(spec: CartridgeSpec) -> TestRound {
+            TestRound {
+                spec: Arc::new(spec),
+                fired: false,
+            }
+        }
+    }
+
+    impl Round for TestRound {
+        fn spec(&self) -> &Arc<CartridgeSpec> {
+            &self.spec
+        }
+
+        fn has_fired(&self) -> bool {
+            self.fired
+        }
+
+        fn fire(&mut self) {
+            self.fired = true;
+        }
+    }
+
+    /// Counts the bullets and recoil that make it out of the gun.
+    #[derive(Default)]
+    struct TestMuzzle {
+        bullets: usize,
+        recoils: usize,
+    }
+
+    impl Muzzle for TestMuzzle {
+        fn spawn(&mut self, _spec: &Arc<CartridgeSpec>, _offset: Vector3, _spread: Orientation) {
+            self.bullets += 1;
+        }
+
+        fn recoil(&mut self, _load: &Load) {
+            self.recoils += 1;
+        }
+    }
+
+    fn seeded_bore() -> Bore {
+        let mut bore = Bore::new(Vector3::zero());
+        bore.seed(1);
+        bore
+    }
+
+    #[test]
+    fn reliable_round_fires() {
+        let mut bore = seeded_bore();
+        let mut muzzle = TestMuzzle::default();
+        let mut round = TestRound::new(CartridgeSpec::default());
+
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::Fired(_));
+        assert!(round.has_fired());
+        assert_eq!(muzzle.bullets, 1);
+        assert_eq!(muzzle.recoils, 1);
+
+        // The hammer falling on the spent case does nothing.
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::DryFired);
+        assert_eq!(muzzle.bullets, 1);
+    }
+
+    #[test]
+    fn misfire_leaves_round_live() {
+        let mut bore = seeded_bore();
+        let mut muzzle = TestMuzzle::default();
+        let mut round = TestRound::new(CartridgeSpec::unreliable(1.0, 0.0, 0.0));
+
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::Misfired);
+        assert!(!round.has_fired());
+        assert!(!bore.is_obstructed());
+        assert!(!bore.is_hanging_fire());
+        assert_eq!(muzzle.bullets, 0);
+        assert_eq!(muzzle.recoils, 0);
+    }
+
+    #[test]
+    fn squib_blocks_next_shot_until_cleared() {
+        let mut bore = seeded_bore();
+        let mut muzzle = TestMuzzle::default();
+
+        let mut squib = TestRound::new(CartridgeSpec::unreliable(0.0, 0.0, 1.0));
+        assert_event!(bore.strike(&mut squib, &mut muzzle), ActionEvent::Squibbed);
+        assert!(squib.has_fired());
+        assert!(bore.is_obstructed());
+
+        // The next round fires into the stuck bullet, and nothing leaves the muzzle.
+        let mut blocked = TestRound::new(CartridgeSpec::default());
+        assert_event!(bore.strike(&mut blocked, &mut muzzle), ActionEvent::BoreObstructed);
+        assert!(blocked.has_fired());
+        assert!(bore.is_obstructed());
+        assert_eq!(muzzle.bullets, 0);
+        assert_eq!(muzzle.recoils, 0);
+
+        // So does a hang-fire that goes off later.
+        let mut late = TestRound::new(CartridgeSpec::default());
+        assert_event!(bore.discharge(&mut late, &mut muzzle), ActionEvent::BoreObstructed);
+        assert_eq!(muzzle.bullets, 0);
+
+        assert!(bore.clear());
+        assert!(!bore.clear());
+
+        let mut round = TestRound::new(CartridgeSpec::default());
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::Fired(_));
+        assert_eq!(muzzle.bullets, 1);
+        assert_eq!(muzzle.recoils, 1);
+    }
+
+    #[test]
+    fn hang_fire_goes_off_after_delay() {
+        let mut bore = seeded_bore();
+        let mut muzzle = TestMuzzle::default();
+        let mut round = TestRound::new(CartridgeSpec::unreliable(0.0, 1.0, 0.0));
+
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::HangFire);
+        assert!(bore.is_hanging_fire());
+        assert!(!round.has_fired());
+        assert_eq!(muzzle.bullets, 0);
+
+        // The delay is somewhere between the shortest and longest hang-fire.
+        assert!(!bore.update(0.05));
+        assert!(bore.is_hanging_fire());
+        assert!(bore.update(1.0));
+        assert!(!bore.is_hanging_fire());
+
+        assert_event!(bore.discharge(&mut round, &mut muzzle), ActionEvent::Fired(_));
+        assert!(round.has_fired());
+        assert_eq!(muzzle.bullets, 1);
+
+        // It only goes off once.
+        assert!(!bore.update(1.0));
+    }
+
+    #[test]
+    fn extract_defuses_hang_fire() {
+        let mut bore = seeded_bore();
+        let mut muzzle = TestMuzzle::default();
+        let mut round = TestRound::new(CartridgeSpec::unreliable(0.0, 1.0, 0.0));
+
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::HangFire);
+        bore.extract(&mut round);
+        assert!(round.has_fired());
+        assert!(!bore.is_hanging_fire());
+        assert!(!bore.update(10.0));
+        assert_eq!(muzzle.bullets, 0);
+        assert_eq!(muzzle.recoils, 0);
+    }
+
+    #[test]
+    fn extract_leaves_live_round_alone() {
+        let mut bore = seeded_bore();
+        let mut round = TestRound::new(CartridgeSpec::default());
+        bore.extract(&mut round);
+        assert!(!round.has_fired());
+    }
+
+    #[test]
+    fn shot_shell_fires_every_pellet_with_one_recoil() {
+        let mut bore = seeded_bore();
+        bore.spread = 0.03;
+        let mut muzzle = TestMuzzle::default();
+        let mut round = TestRound::new(CartridgeSpec {
+            pellets: 9,
+            .. CartridgeSpec::default()
+        });
+
+        assert_event!(bore.strike(&mut round, &mut muzzle), ActionEvent::Fired(_));
+        assert_eq!(muzzle.bullets, 9);
+        assert_eq!(muzzle.recoils, 1);
+    }
+
+    #[test]
+    fn same_seed_gives_same_shots() {
+        let spec = CartridgeSpec::unreliable(0.2, 0.2, 0.0);
+        let mut first = seeded_bore();
+        let mut second = seeded_bore();
+        for _ in 0..100 {
+            let first_event = first.strike(&mut TestRound::new(spec.clone()), &mut TestMuzzle::default());
+            let second_event = second.strike(&mut TestRound::new(spec.clone()), &mut TestMuzzle::default());
+            assert_eq!(format!("{:?}", first_event), format!("{:?}", second_event));
+            first.defuse();
+            second.defuse();
+        }
+    }
+}
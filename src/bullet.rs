@@ -0,0 +1,274 @@
+use ballistics::{self, Atmosphere, Hit};
+use cartridge::CartridgeSpec;
+use collision::{CollisionWorld, LAYER_WORLD};
+use gunship::*;
+use gunship::math::*;
+use gunship::mesh_renderer::MeshRenderer;
+use gunship::resource::Mesh;
+use gunship::transform::Transform;
+use physics::{self, Integrator};
+use std::sync::{Arc, Mutex};
+
+/// The layers bullets can hit. Casings are too small to stop a bullet, and the player's own
+/// capsule would catch their shots as they leave the muzzle.
+const BULLET_MASK: u32 = LAYER_WORLD;
+
+/// The outcome of updating a `Bullet` for a frame.
+#[derive(Debug, Clone, Copy)]
+pub enum BulletState {
+    /// The bullet is still in flight.
+    Flying,
+
+    /// The bullet struck a collider and should be despawned.
+    Hit(Hit),
+
+    /// The bullet exceeded its maximum range or lifetime without hitting anything.
+    Expired,
+}
+
+/// Tracks state for a bullet that's been fired.
+#[derive(Debug)]
+pub struct Bullet {
+    transform: Transform,
+    mesh_renderer: MeshRenderer,
+
+    /// The type of cartridge the bullet was fired from.
+    pub spec: Arc<CartridgeSpec>,
+
+    /// The maximum distance (in meters) the bullet can travel before it despawns.
+    pub max_range: f32,
+
+    /// The maximum time (in seconds) the bullet can be in flight before it despawns.
+    pub max_lifetime: f32,
+
+    velocity: Vector3,
+    distance: f32,
+    lifetime: f32,
+}
+
+impl Bullet {
+    /// Creates a bullet fired from a cartridge of type `spec`, leaving the muzzle at `position`.
+    ///
+    /// `gun_velocity` is the velocity of the gun when it was fired, which the bullet inherits on
+    /// top of its muzzle velocity.
+    pub fn new(
+        mesh: &Mesh,
+        spec: Arc<CartridgeSpec>,
+        position: Point,
+        orientation: Orientation,
+        gun_velocity: Vector3,
+    ) -> Bullet {
+        let transform = Transform::new();
+        let mesh_renderer = MeshRenderer::new(mesh, &transform);
+
+        let mut bullet = Bullet {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+
+            spec: spec.clone(),
+
+            max_range: 500.0,
+            max_lifetime: 5.0,
+
+            velocity: Vector3::zero(),
+            distance: 0.0,
+            lifetime: 0.0,
+        };
+        bullet.fire(spec, position, orientation, gun_velocity);
+        bullet
+    }
+
+    /// Resets the bullet as if it had just been fired. See `Bullet::new()` for details.
+    ///
+    /// This lets the pool reuse bullets that have already hit something instead of creating a new
+    /// transform and renderer for every shot.
+    pub fn fire(
+        &mut self,
+        spec: Arc<CartridgeSpec>,
+        position: Point,
+        orientation: Orientation,
+        gun_velocity: Vector3,
+    ) {
+        self.transform.set_position(position);
+        self.transform.set_orientation(orientation);
+
+        // Tracers are drawn as a long streak, everything else is drawn at roughly the size of the
+        // actual bullet.
+        // TODO: Remove this once we have a proper bullet mesh.
+        if spec.tracer {
+            self.transform.set_scale(Vector3::new(0.1, 0.1, 1.0));
+        } else {
+            self.transform.set_scale(Vector3::new(spec.caliber, spec.caliber, spec.caliber * 2.0));
+        }
+
+        self.velocity = self.transform.forward() * spec.load.muzzle_velocity + gun_velocity;
+        self.distance = 0.0;
+        self.lifetime = 0.0;
+        self.spec = spec;
+    }
+
+    /// Hides the bullet once it's done flying so that it can wait in the pool to be fired again.
+    ///
+    /// TODO: Disable the renderer instead of collapsing the bullet once gunship supports it.
+    pub fn hide(&mut self) {
+        self.transform.set_scale(Vector3::zero());
+    }
+
+    /// Gets the total distance the bullet has travelled since it was fired.
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Gets the bullet's current velocity in meters per second.
+    pub fn velocity(&self) -> Vector3 {
+        self.velocity
+    }
+
+    /// Moves the bullet along its trajectory for `delta` seconds, sweeping the path it travelled
+    /// against `world`.
+    ///
+    /// The frame is split into substeps of at most `ballistics::SUBSTEP` so that drop and drag
+    /// stay accurate at long range. Each substep is swept separately, so the bullet hits whatever
+    /// is under its arc rather than under the straight line between frames.
+    pub fn update(&mut self, world: &CollisionWorld, atmosphere: &Atmosphere, delta: f32) -> BulletState {
+        let mut remaining = delta;
+        while remaining > 0.0 {
+            let delta = remaining.min(ballistics::SUBSTEP);
+            remaining -= delta;
+
+            let spec = &self.spec;
+            let (displacement, velocity) = Integrator::Rk4.integrate(
+                self.velocity,
+                delta,
+                |_, velocity| ballistics::acceleration(spec, atmosphere, velocity),
+            );
+
+            let start = self.transform.position();
+            let end = start + displacement;
+            if let Some(hit) = ballistics::sweep(world, start, end, self.distance, BULLET_MASK) {
+                self.transform.set_position(hit.point);
+                self.distance = hit.distance;
+                return BulletState::Hit(hit);
+            }
+
+            self.transform.set_position(end);
+            self.velocity = velocity;
+            self.distance += displacement.magnitude();
+            self.lifetime += delta;
+
+            if self.distance >= self.max_range || self.lifetime >= self.max_lifetime {
+                return BulletState::Expired;
+            }
+        }
+
+        // Keep the bullet pointing along its trajectory as it arcs.
+        let forward = self.transform.forward();
+        let direction = self.velocity.normalized();
+        let axis = forward.cross(direction);
+        let sin_angle = axis.magnitude();
+        if sin_angle > ::std::f32::EPSILON {
+            let angle = sin_angle.atan2(forward.dot(direction));
+            let orientation = physics::rotate_orientation(self.transform.orientation(), axis / sin_angle * angle);
+            self.transform.set_orientation(orientation);
+        }
+
+        BulletState::Flying
+    }
+}
+
+/// Owns every bullet in flight and updates them together once per frame.
+///
+/// Guns spawn bullets into the pool, and the pool hides them as soon as they hit something or
+/// expire. Hidden bullets are kept around and reused for later shots.
+#[derive(Debug)]
+pub struct BulletPool {
+    bullets: Vec<Bullet>,
+    free: Vec<Bullet>,
+    hits: Vec<Hit>,
+
+    mesh: Arc<Mesh>,
+    collision_world: Arc<Mutex<CollisionWorld>>,
+    atmosphere: Arc<Mutex<Atmosphere>>,
+}
+
+impl BulletPool {
+    pub fn new(
+        mesh: Arc<Mesh>,
+        collision_world: Arc<Mutex<CollisionWorld>>,
+        atmosphere: Arc<Mutex<Atmosphere>>,
+    ) -> BulletPool {
+        BulletPool {
+            bullets: Vec::new(),
+            free: Vec::new(),
+            hits: Vec::new(),
+
+            mesh: mesh,
+            collision_world: collision_world,
+            atmosphere: atmosphere,
+        }
+    }
+
+    /// Fires a bullet, reusing a hidden one if there are any. See `Bullet::new()` for details.
+    pub fn spawn(
+        &mut self,
+        spec: Arc<CartridgeSpec>,
+        position: Point,
+        orientation: Orientation,
+        gun_velocity: Vector3,
+    ) {
+        let bullet = match self.free.pop() {
+            Some(mut bullet) => {
+                bullet.fire(spec, position, orientation, gun_velocity);
+                bullet
+            },
+            None => Bullet::new(&self.mesh, spec, position, orientation, gun_velocity),
+        };
+        self.bullets.push(bullet);
+    }
+
+    /// Gets the number of bullets currently in flight.
+    pub fn len(&self) -> usize {
+        self.bullets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bullets.is_empty()
+    }
+
+    /// Gets the hits that happened during the last `update()`.
+    pub fn hits(&self) -> &[Hit] {
+        &self.hits
+    }
+
+    /// Advances every bullet for the frame, hiding any that hit something or expired.
+    ///
+    /// The hits that happened during the frame can be read with `hits()` until the next update.
+    pub fn update(&mut self) {
+        let atmosphere = *self.atmosphere.lock().unwrap();
+        let world = self.collision_world.lock().unwrap();
+        let delta = time::delta_f32();
+
+        self.hits.clear();
+        let mut index = 0;
+        while index < self.bullets.len() {
+            let done = match self.bullets[index].update(&world, &atmosphere, delta) {
+                BulletState::Flying => false,
+                BulletState::Hit(hit) => {
+                    self.hits.push(hit);
+                    true
+                },
+                BulletState::Expired => true,
+            };
+
+            // Order doesn't matter, so fill the gap with the last bullet rather than shifting
+            // everything down.
+            if done {
+                let mut bullet = self.bullets.swap_remove(index);
+                bullet.hide();
+                self.free.push(bullet);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
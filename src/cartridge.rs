@@ -0,0 +1,261 @@
+use config::Config;
+use random::Random;
+use recoil::Load;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// The shortest delay (in seconds) before a hang-fire goes off.
+const HANG_FIRE_MIN_DELAY: f32 = 0.1;
+
+/// The longest delay (in seconds) before a hang-fire goes off.
+const HANG_FIRE_MAX_DELAY: f32 = 1.0;
+
+/// What happens when a cartridge's primer is struck.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ignition {
+    /// The cartridge fires normally.
+    Fire,
+
+    /// Nothing happens. The cartridge is still live and may go off if struck again.
+    Misfire,
+
+    /// The powder is slow to light, and the cartridge fires after the specified delay in seconds.
+    HangFire(f32),
+
+    /// Only the primer goes off, which pushes the bullet partway down the barrel where it gets
+    /// stuck.
+    Squib,
+}
+
+/// Describes a type of cartridge: its projectile, propellant, and how it behaves on impact.
+///
+/// Specs are shared between every `Cartridge` of the same type.
+#[derive(Debug, Clone)]
+pub struct CartridgeSpec {
+    /// The display name of the cartridge, e.g. ".357 Magnum".
+    pub name: String,
+
+    /// The chamber the cartridge is made to fit, e.g. "357_magnum". Guns list the chamberings
+    /// they can be loaded with.
+    pub chambering: String,
+
+    /// The diameter of the bullet in meters. For shot shells this is the diameter of each pellet.
+    pub caliber: f32,
+
+    /// The bullet mass, powder mass, and muzzle velocity of the cartridge. For shot shells the
+    /// bullet mass is the mass of the whole payload.
+    pub load: Load,
+
+    /// The number of projectiles fired at once, 1 for anything but shot shells.
+    pub pellets: usize,
+
+    /// The bullet's drag coefficient, used to calculate air resistance in flight. See
+    /// `ballistic_coefficient()`.
+    pub drag_coefficient: f32,
+
+    /// The damage dealt by the bullet on a direct hit.
+    pub damage: f32,
+
+    /// How far (in meters) the bullet can penetrate into a target.
+    pub penetration: f32,
+
+    /// Tracer rounds leave a visible streak while in flight.
+    pub tracer: bool,
+
+    /// The probability (in the range [0, 1]) that the cartridge fails to fire when struck.
+    pub misfire_chance: f32,
+
+    /// The probability (in the range [0, 1]) that the cartridge fires late when struck.
+    pub hang_fire_chance: f32,
+
+    /// The probability (in the range [0, 1]) that the cartridge lodges its bullet in the barrel.
+    pub squib_chance: f32,
+}
+
+impl CartridgeSpec {
+    /// Reads the spec from the keys in `section` of `config`.
+    ///
+    /// Returns `None` if any of the required keys are missing or malformed. `chambering`,
+    /// `pellets`, and `tracer` are optional and default to the section name, 1, and `false`. The
+    /// chances of the cartridge failing are optional and default to 0.
+    pub fn from_config(config: &Config, section: &str) -> Option<CartridgeSpec> {
+        let key = |name: &str| format!("{}.{}", section, name);
+
+        Some(CartridgeSpec {
+            name: config.get(&*key("name")).unwrap_or_else(|| section.into()),
+            chambering: config.get(&*key("chambering")).unwrap_or_else(|| section.into()),
+            caliber: config.get(&*key("caliber"))?,
+            load: Load {
+                bullet_mass: config.get(&*key("bullet_mass"))?,
+                powder_mass: config.get(&*key("powder_mass"))?,
+                muzzle_velocity: config.get(&*key("muzzle_velocity"))?,
+            },
+            pellets: config.get_or(&*key("pellets"), 1),
+            drag_coefficient: config.get(&*key("drag_coefficient"))?,
+            damage: config.get(&*key("damage"))?,
+            penetration: config.get(&*key("penetration"))?,
+            tracer: config.get_or(&*key("tracer"), false),
+            misfire_chance: config.get_or(&*key("misfire_chance"), 0.0),
+            hang_fire_chance: config.get_or(&*key("hang_fire_chance"), 0.0),
+            squib_chance: config.get_or(&*key("squib_chance"), 0.0),
+        })
+    }
+
+    /// Checks if the cartridge fits a gun chambered for any of `chamberings`.
+    pub fn fits(&self, chamberings: &[String]) -> bool {
+        chamberings.iter().any(|chambering| *chambering == self.chambering)
+    }
+
+    /// Decides what happens when a cartridge of this type is struck.
+    ///
+    /// The outcome only depends on the spec and the state of `random`, so the same seed always
+    /// gives the same sequence of outcomes.
+    pub fn ignite(&self, random: &mut Random) -> Ignition {
+        let roll = random.next_f32();
+        if roll < self.misfire_chance {
+            return Ignition::Misfire;
+        }
+
+        let roll = roll - self.misfire_chance;
+        if roll < self.hang_fire_chance {
+            return Ignition::HangFire(random.range(HANG_FIRE_MIN_DELAY, HANG_FIRE_MAX_DELAY));
+        }
+
+        let roll = roll - self.hang_fire_chance;
+        if roll < self.squib_chance {
+            return Ignition::Squib;
+        }
+
+        Ignition::Fire
+    }
+
+    /// Gets the mass of each individual projectile in kilograms.
+    pub fn pellet_mass(&self) -> f32 {
+        self.load.bullet_mass / self.pellets as f32
+    }
+
+    /// Calculates the bullet's ballistic coefficient in kilograms per square meter.
+    ///
+    /// This is the bullet's mass divided by its drag coefficient and cross-sectional area. Higher
+    /// values mean the bullet holds its velocity better.
+    pub fn ballistic_coefficient(&self) -> f32 {
+        let radius = self.caliber * 0.5;
+        let area = PI * radius * radius;
+        self.pellet_mass() / (self.drag_coefficient * area)
+    }
+
+    /// Reads a spec from every section of `config`, skipping any that are malformed.
+    pub fn load_all(config: &Config) -> Vec<Arc<CartridgeSpec>> {
+        config.sections()
+            .iter()
+            .filter_map(|section| CartridgeSpec::from_config(config, section))
+            .map(Arc::new)
+            .collect()
+    }
+}
+
+impl Default for CartridgeSpec {
+    /// A 158 grain .357 Magnum round.
+    fn default() -> CartridgeSpec {
+        CartridgeSpec {
+            name: ".357 Magnum".into(),
+            chambering: "357_magnum".into(),
+            caliber: 0.00907,
+            load: Load::default(),
+            pellets: 1,
+            drag_coefficient: 0.3,
+            damage: 45.0,
+            penetration: 0.3,
+            tracer: false,
+            misfire_chance: 0.0,
+            hang_fire_chance: 0.0,
+            squib_chance: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+impl CartridgeSpec {
+    /// Creates a .357 Magnum spec that fails with the specified chances, for testing.
+    pub fn unreliable(misfire_chance: f32, hang_fire_chance: f32, squib_chance: f32) -> CartridgeSpec {
+        CartridgeSpec {
+            misfire_chance: misfire_chance,
+            hang_fire_chance: hang_fire_chance,
+            squib_chance: squib_chance,
+            .. CartridgeSpec::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How many times each spec is struck when measuring how often it fails.
+    const STRIKES: usize = 100_000;
+
+    /// How far the measured rate of each outcome can be from the configured chance.
+    const TOLERANCE: f32 = 0.01;
+
+    /// Strikes `STRIKES` cartridges of type `spec`, returning the rates of misfires, hang-fires,
+    /// and squibs.
+    fn failure_rates(spec: &CartridgeSpec, seed: u32) -> (f32, f32, f32) {
+        let mut random = Random::new(seed);
+        let (mut misfires, mut hang_fires, mut squibs) = (0, 0, 0);
+        for _ in 0..STRIKES {
+            match spec.ignite(&mut random) {
+                Ignition::Fire => {},
+                Ignition::Misfire => misfires += 1,
+                Ignition::HangFire(_) => hang_fires += 1,
+                Ignition::Squib => squibs += 1,
+            }
+        }
+
+        let rate = |count: usize| count as f32 / STRIKES as f32;
+        (rate(misfires), rate(hang_fires), rate(squibs))
+    }
+
+    #[test]
+    fn reliable_cartridges_always_fire() {
+        let spec = CartridgeSpec::default();
+        let mut random = Random::new(1);
+        for _ in 0..STRIKES {
+            assert_eq!(spec.ignite(&mut random), Ignition::Fire);
+        }
+    }
+
+    #[test]
+    fn failures_happen_at_their_configured_chances() {
+        let spec = CartridgeSpec::unreliable(0.1, 0.2, 0.05);
+        for &seed in &[1, 7, 1234] {
+            let (misfire, hang_fire, squib) = failure_rates(&spec, seed);
+            assert!((misfire - spec.misfire_chance).abs() < TOLERANCE, "misfire rate {} with seed {}", misfire, seed);
+            assert!((hang_fire - spec.hang_fire_chance).abs() < TOLERANCE, "hang-fire rate {} with seed {}", hang_fire, seed);
+            assert!((squib - spec.squib_chance).abs() < TOLERANCE, "squib rate {} with seed {}", squib, seed);
+        }
+    }
+
+    #[test]
+    fn hang_fire_delays_are_in_range() {
+        let spec = CartridgeSpec::unreliable(0.0, 1.0, 0.0);
+        let mut random = Random::new(1);
+        for _ in 0..1000 {
+            match spec.ignite(&mut random) {
+                Ignition::HangFire(delay) => {
+                    assert!(delay >= HANG_FIRE_MIN_DELAY && delay <= HANG_FIRE_MAX_DELAY, "delay {}", delay);
+                },
+                ignition => panic!("expected a hang-fire, got {:?}", ignition),
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_outcomes() {
+        let spec = CartridgeSpec::unreliable(0.1, 0.2, 0.05);
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        for _ in 0..1000 {
+            assert_eq!(spec.ignite(&mut first), spec.ignite(&mut second));
+        }
+    }
+}
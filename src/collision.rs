@@ -0,0 +1,744 @@
+use gunship::math::*;
+use physics::Rigidbody;
+
+/// The layer for the static level geometry.
+pub const LAYER_WORLD: u32 = 1 << 0;
+
+/// The layer for things that move around the world and push each other out of the way, like the
+/// player.
+pub const LAYER_ACTOR: u32 = 1 << 1;
+
+/// The layer for small loose objects like ejected casings, which shouldn't get in anyone's way.
+pub const LAYER_DEBRIS: u32 = 1 << 2;
+
+/// A mask that includes every layer.
+pub const LAYER_ALL: u32 = !0;
+
+/// Identifies a collider that has been added to a `CollisionWorld`.
+///
+/// Each id remembers which generation of its slot it was created for, so an id that's held onto
+/// after its collider is removed won't refer to whatever collider reuses the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColliderId {
+    index: usize,
+    generation: u32,
+}
+
+/// The geometric shape of a collider, defined relative to the collider's position and
+/// orientation.
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    Sphere { radius: f32 },
+
+    /// A box that stays aligned to the world axes regardless of orientation.
+    Aabb { half_extents: Vector3 },
+
+    /// A box that rotates with the collider's orientation.
+    Obb { half_extents: Vector3 },
+
+    /// A cylinder capped with hemispheres, running along the collider's local up axis.
+    ///
+    /// `half_height` is the distance from the center to the center of either cap, so the total
+    /// height of the capsule is `2.0 * (half_height + radius)`.
+    Capsule { radius: f32, half_height: f32 },
+
+    /// An infinite plane passing through the collider's position, facing along the collider's
+    /// local up axis. Anything below the plane is considered to be inside it.
+    Plane,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Collider {
+    pub shape: Shape,
+    pub position: Point,
+    pub orientation: Orientation,
+
+    /// Static colliders never move in response to contacts, so overlaps between two static
+    /// colliders aren't reported.
+    pub is_static: bool,
+
+    /// The layers the collider is on.
+    pub layer: u32,
+
+    /// The layers the collider reports contacts with. A contact is only reported if each
+    /// collider is on a layer in the other's mask.
+    pub mask: u32,
+
+    /// How much of the relative velocity along the contact normal is preserved on impact, in the
+    /// range [0, 1].
+    pub restitution: f32,
+
+    /// The coefficient of friction used when resolving contacts.
+    pub friction: f32,
+}
+
+impl Collider {
+    /// Creates a new static collider with default material properties.
+    pub fn new(shape: Shape, position: Point) -> Collider {
+        Collider {
+            shape: shape,
+            position: position,
+            orientation: Orientation::default(),
+
+            is_static: true,
+            layer: LAYER_WORLD,
+            mask: LAYER_ALL,
+
+            restitution: 0.2,
+            friction: 0.5,
+        }
+    }
+
+    /// Creates a new collider that's expected to be moved by a `Rigidbody`.
+    pub fn dynamic(shape: Shape, position: Point) -> Collider {
+        Collider {
+            is_static: false,
+            layer: LAYER_ACTOR,
+            .. Collider::new(shape, position)
+        }
+    }
+
+    /// Checks if contacts between the two colliders should be reported.
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        !(self.is_static && other.is_static)
+            && self.layer & other.mask != 0
+            && other.layer & self.mask != 0
+    }
+
+    /// Moves the collider to match the rigidbody it's attached to.
+    pub fn sync(&mut self, rigidbody: &Rigidbody) {
+        self.position = rigidbody.position();
+        self.orientation = rigidbody.orientation();
+    }
+
+    /// Casts a ray against the collider, returning the distance along the ray and the surface
+    /// normal of the first intersection.
+    ///
+    /// `direction` must be normalized. Rays that start inside the collider don't report a hit.
+    pub fn raycast(&self, origin: Point, direction: Vector3, max_distance: f32) -> Option<(f32, Vector3)> {
+        let result = match self.solid() {
+            Solid::Round { start, end, radius } => raycast_round(origin, direction, start, end, radius),
+            Solid::Box(ref bounds) => raycast_box(origin, direction, bounds),
+            Solid::Plane { point, normal } => raycast_plane(origin, direction, point, normal),
+        };
+
+        match result {
+            Some((distance, normal)) if distance <= max_distance => Some((distance, normal)),
+            _ => None,
+        }
+    }
+
+    /// Gets the world space representation of the collider used for intersection tests.
+    fn solid(&self) -> Solid {
+        match self.shape {
+            Shape::Sphere { radius } => Solid::Round {
+                start: self.position,
+                end: self.position,
+                radius: radius,
+            },
+
+            Shape::Capsule { radius, half_height } => {
+                let offset = self.orientation.up() * half_height;
+                Solid::Round {
+                    start: self.position - offset,
+                    end: self.position + offset,
+                    radius: radius,
+                }
+            },
+
+            Shape::Aabb { half_extents } => Solid::Box(Bounds {
+                center: self.position,
+                axes: [Vector3::new(1.0, 0.0, 0.0), Vector3::up(), Vector3::new(0.0, 0.0, 1.0)],
+                half_extents: [half_extents.x, half_extents.y, half_extents.z],
+            }),
+
+            Shape::Obb { half_extents } => Solid::Box(Bounds {
+                center: self.position,
+                axes: [
+                    self.orientation * Vector3::new(1.0, 0.0, 0.0),
+                    self.orientation * Vector3::up(),
+                    self.orientation * Vector3::new(0.0, 0.0, 1.0),
+                ],
+                half_extents: [half_extents.x, half_extents.y, half_extents.z],
+            }),
+
+            Shape::Plane => Solid::Plane {
+                point: self.position,
+                normal: self.orientation.up(),
+            },
+        }
+    }
+}
+
+/// The result of a successful raycast against a `CollisionWorld`.
+#[derive(Debug, Clone, Copy)]
+pub struct RaycastHit {
+    pub collider: ColliderId,
+    pub point: Point,
+    pub normal: Vector3,
+    pub distance: f32,
+}
+
+/// Describes two colliders overlapping.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub a: ColliderId,
+    pub b: ColliderId,
+
+    /// The approximate point of contact in world space.
+    pub point: Point,
+
+    /// The contact normal, pointing from `b` towards `a`.
+    ///
+    /// Moving `a` by `normal * depth` separates the two colliders.
+    pub normal: Vector3,
+
+    /// How far the two colliders are overlapping along `normal`.
+    pub depth: f32,
+
+    /// The combined restitution of the two colliders.
+    pub restitution: f32,
+
+    /// The combined friction of the two colliders.
+    pub friction: f32,
+}
+
+impl Contact {
+    /// Swaps `a` and `b`, flipping the normal to match.
+    pub fn flipped(self) -> Contact {
+        Contact {
+            a: self.b,
+            b: self.a,
+            normal: -self.normal,
+            .. self
+        }
+    }
+}
+
+/// A place in the `CollisionWorld` for a collider.
+#[derive(Debug)]
+struct Slot {
+    /// Bumped every time the slot's collider is removed, invalidating any ids for it.
+    generation: u32,
+    collider: Option<Collider>,
+}
+
+/// Tracks all colliders in the scene.
+#[derive(Debug)]
+pub struct CollisionWorld {
+    /// Colliders are never moved once added so that `ColliderId` can index directly into the
+    /// list. Removed colliders leave an empty slot behind until it's reused.
+    slots: Vec<Slot>,
+
+    /// The indices of the empty slots.
+    free: Vec<usize>,
+
+    /// The overlaps found during the last call to `step()`.
+    contacts: Vec<Contact>,
+}
+
+impl CollisionWorld {
+    pub fn new() -> CollisionWorld {
+        CollisionWorld {
+            slots: Vec::new(),
+            free: Vec::new(),
+            contacts: Vec::new(),
+        }
+    }
+
+    /// Adds a collider to the world, returning the id used to refer to it later.
+    ///
+    /// Slots left behind by removed colliders are reused.
+    pub fn add(&mut self, collider: Collider) -> ColliderId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, collider: None });
+                self.slots.len() - 1
+            },
+        };
+
+        let slot = &mut self.slots[index];
+        slot.collider = Some(collider);
+        ColliderId {
+            index: index,
+            generation: slot.generation,
+        }
+    }
+
+    /// Removes the collider from the world, returning it if it was still present.
+    pub fn remove(&mut self, id: ColliderId) -> Option<Collider> {
+        let collider = self.slot_mut(id)?.collider.take();
+
+        // Invalidate every id for the collider, including `id`.
+        self.slots[id.index].generation = self.slots[id.index].generation.wrapping_add(1);
+        self.free.push(id.index);
+        collider
+    }
+
+    pub fn get(&self, id: ColliderId) -> Option<&Collider> {
+        self.slots
+            .get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.collider.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: ColliderId) -> Option<&mut Collider> {
+        self.slot_mut(id).and_then(|slot| slot.collider.as_mut())
+    }
+
+    /// Gets the slot for `id`, as long as it's still holding the collider the id was made for.
+    fn slot_mut(&mut self, id: ColliderId) -> Option<&mut Slot> {
+        match self.slots.get_mut(id.index) {
+            Some(slot) if slot.generation == id.generation && slot.collider.is_some() => Some(slot),
+            _ => None,
+        }
+    }
+
+    /// Finds the closest collider hit by the ray within `max_distance`.
+    ///
+    /// `direction` must be normalized. Only colliders on one of the layers in `mask` can be hit.
+    pub fn raycast(&self, origin: Point, direction: Vector3, max_distance: f32, mask: u32) -> Option<RaycastHit> {
+        let mut closest: Option<RaycastHit> = None;
+
+        for (index, slot) in self.slots.iter().enumerate() {
+            let collider = match slot.collider.as_ref() {
+                Some(collider) if collider.layer & mask != 0 => collider,
+                _ => continue,
+            };
+
+            let max_distance = closest.map_or(max_distance, |hit| hit.distance);
+            if let Some((distance, normal)) = collider.raycast(origin, direction, max_distance) {
+                closest = Some(RaycastHit {
+                    collider: ColliderId { index: index, generation: slot.generation },
+                    point: origin + direction * distance,
+                    normal: normal,
+                    distance: distance,
+                });
+            }
+        }
+
+        closest
+    }
+
+    /// Tests every pair of colliders for overlap, replacing the contacts from the previous step.
+    ///
+    /// Call this once per physics step after all dynamic colliders have been synced with their
+    /// transforms.
+    pub fn step(&mut self) {
+        self.contacts.clear();
+
+        for (index_a, slot_a) in self.slots.iter().enumerate() {
+            let collider_a = match slot_a.collider.as_ref() {
+                Some(collider) => collider,
+                None => continue,
+            };
+
+            for (index_b, slot_b) in self.slots.iter().enumerate().skip(index_a + 1) {
+                let collider_b = match slot_b.collider.as_ref() {
+                    Some(collider) => collider,
+                    None => continue,
+                };
+
+                if !collider_a.interacts_with(collider_b) {
+                    continue;
+                }
+
+                if let Some((point, normal, depth)) = collide(&collider_a.solid(), &collider_b.solid()) {
+                    self.contacts.push(Contact {
+                        a: ColliderId { index: index_a, generation: slot_a.generation },
+                        b: ColliderId { index: index_b, generation: slot_b.generation },
+                        point: point,
+                        normal: normal,
+                        depth: depth,
+                        restitution: collider_a.restitution.max(collider_b.restitution),
+                        friction: (collider_a.friction * collider_b.friction).sqrt(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Gets all contacts found during the last step.
+    pub fn contacts(&self) -> &[Contact] {
+        &*self.contacts
+    }
+
+    /// Gets the contacts involving `id` from the last step, oriented so that `id` is always `a`.
+    pub fn contacts_with(&self, id: ColliderId) -> Vec<Contact> {
+        self.contacts
+            .iter()
+            .filter_map(|&contact| {
+                if contact.a == id {
+                    Some(contact)
+                } else if contact.b == id {
+                    Some(contact.flipped())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// The world space geometry of a collider.
+///
+/// Spheres and capsules are both represented as a segment with a radius, and both box types are
+/// represented as an oriented box, which keeps the number of pairwise tests manageable.
+#[derive(Debug, Clone, Copy)]
+enum Solid {
+    Round { start: Point, end: Point, radius: f32 },
+    Box(Bounds),
+    Plane { point: Point, normal: Vector3 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    center: Point,
+    axes: [Vector3; 3],
+    half_extents: [f32; 3],
+}
+
+impl Bounds {
+    fn closest_point(&self, point: Point) -> Point {
+        let offset = point - self.center;
+        let mut result = self.center;
+        for axis in 0..3 {
+            let distance = offset.dot(self.axes[axis]).clamp(-self.half_extents[axis], self.half_extents[axis]);
+            result = result + self.axes[axis] * distance;
+        }
+
+        result
+    }
+
+    fn corners(&self) -> [Point; 8] {
+        let mut corners = [self.center; 8];
+        for (index, corner) in corners.iter_mut().enumerate() {
+            for axis in 0..3 {
+                let sign = if index & (1 << axis) == 0 { -1.0 } else { 1.0 };
+                *corner = *corner + self.axes[axis] * self.half_extents[axis] * sign;
+            }
+        }
+
+        corners
+    }
+
+    /// Projects the box onto `axis`, returning the half-length of the projection.
+    fn projected_radius(&self, axis: Vector3) -> f32 {
+        (0..3).fold(0.0, |sum, index| sum + self.half_extents[index] * self.axes[index].dot(axis).abs())
+    }
+}
+
+/// Tests two solids for overlap, returning the contact point, the normal pointing from `b`
+/// towards `a`, and the penetration depth.
+fn collide(a: &Solid, b: &Solid) -> Option<(Point, Vector3, f32)> {
+    let flip = |result: Option<(Point, Vector3, f32)>| {
+        result.map(|(point, normal, depth)| (point, -normal, depth))
+    };
+
+    match (*a, *b) {
+        (Solid::Round { start: start_a, end: end_a, radius: radius_a },
+         Solid::Round { start: start_b, end: end_b, radius: radius_b }) => {
+            let (point_a, point_b) = closest_points_segments(start_a, end_a, start_b, end_b);
+            collide_points(point_a, radius_a, point_b, radius_b)
+        },
+
+        (Solid::Round { start, end, radius }, Solid::Box(ref bounds)) => collide_round_box(start, end, radius, bounds),
+        (Solid::Box(ref bounds), Solid::Round { start, end, radius }) => flip(collide_round_box(start, end, radius, bounds)),
+
+        (Solid::Round { start, end, radius }, Solid::Plane { point, normal }) => {
+            collide_plane(&[start, end], radius, point, normal)
+        },
+        (Solid::Plane { point, normal }, Solid::Round { start, end, radius }) => {
+            flip(collide_plane(&[start, end], radius, point, normal))
+        },
+
+        (Solid::Box(ref bounds), Solid::Plane { point, normal }) => collide_plane(&bounds.corners(), 0.0, point, normal),
+        (Solid::Plane { point, normal }, Solid::Box(ref bounds)) => flip(collide_plane(&bounds.corners(), 0.0, point, normal)),
+
+        (Solid::Box(ref bounds_a), Solid::Box(ref bounds_b)) => collide_boxes(bounds_a, bounds_b),
+
+        // Planes are infinite, so two planes either always overlap or never do. Either way there's
+        // no meaningful contact to report.
+        (Solid::Plane { .. }, Solid::Plane { .. }) => None,
+    }
+}
+
+fn collide_points(point_a: Point, radius_a: f32, point_b: Point, radius_b: f32) -> Option<(Point, Vector3, f32)> {
+    let offset = point_a - point_b;
+    let distance = offset.magnitude();
+    if distance >= radius_a + radius_b {
+        return None;
+    }
+
+    // If the centers are coincident there's no meaningful direction, so push straight up.
+    let normal = if distance > ::std::f32::EPSILON { offset / distance } else { Vector3::up() };
+    Some((point_b + normal * radius_b, normal, radius_a + radius_b - distance))
+}
+
+fn collide_round_box(start: Point, end: Point, radius: f32, bounds: &Bounds) -> Option<(Point, Vector3, f32)> {
+    // Find the closest points between the segment and the box by bouncing between the two. This
+    // isn't exact for every configuration but converges quickly for the shapes we use.
+    let mut on_segment = closest_point_on_segment(bounds.center, start, end);
+    let mut on_box = bounds.closest_point(on_segment);
+    on_segment = closest_point_on_segment(on_box, start, end);
+    on_box = bounds.closest_point(on_segment);
+
+    let offset = on_segment - on_box;
+    let distance = offset.magnitude();
+    if distance > ::std::f32::EPSILON {
+        if distance >= radius {
+            return None;
+        }
+
+        return Some((on_box, offset / distance, radius - distance));
+    }
+
+    // The segment passes through the box, so push out along the axis of least penetration.
+    let local = on_segment - bounds.center;
+    let mut best = (::std::f32::MAX, Vector3::up());
+    for axis in 0..3 {
+        let projection = local.dot(bounds.axes[axis]);
+        let penetration = bounds.half_extents[axis] - projection.abs();
+        if penetration < best.0 {
+            let sign = if projection < 0.0 { -1.0 } else { 1.0 };
+            best = (penetration, bounds.axes[axis] * sign);
+        }
+    }
+
+    Some((on_box, best.1, best.0 + radius))
+}
+
+fn collide_plane(points: &[Point], radius: f32, plane_point: Point, normal: Vector3) -> Option<(Point, Vector3, f32)> {
+    let (deepest, distance) = points
+        .iter()
+        .map(|&point| (point, (point - plane_point).dot(normal)))
+        .fold((plane_point, ::std::f32::MAX), |best, next| if next.1 < best.1 { next } else { best });
+
+    if distance >= radius {
+        return None;
+    }
+
+    Some((deepest - normal * distance, normal, radius - distance))
+}
+
+/// Tests two boxes for overlap using the separating axis theorem.
+fn collide_boxes(a: &Bounds, b: &Bounds) -> Option<(Point, Vector3, f32)> {
+    let mut axes = Vec::with_capacity(15);
+    axes.extend_from_slice(&a.axes);
+    axes.extend_from_slice(&b.axes);
+    for axis_a in &a.axes {
+        for axis_b in &b.axes {
+            axes.push(axis_a.cross(*axis_b));
+        }
+    }
+
+    let between = a.center - b.center;
+    let mut best = (::std::f32::MAX, Vector3::up());
+
+    for axis in axes {
+        // Cross products of nearly parallel edges don't give a useful axis.
+        let length = axis.magnitude();
+        if length < 1e-4 {
+            continue;
+        }
+        let axis = axis / length;
+
+        let distance = between.dot(axis);
+        let overlap = a.projected_radius(axis) + b.projected_radius(axis) - distance.abs();
+        if overlap <= 0.0 {
+            return None;
+        }
+
+        if overlap < best.0 {
+            let normal = if distance < 0.0 { -axis } else { axis };
+            best = (overlap, normal);
+        }
+    }
+
+    Some((b.closest_point(a.center), best.1, best.0))
+}
+
+fn closest_point_on_segment(point: Point, start: Point, end: Point) -> Point {
+    let segment = end - start;
+    let length_squared = segment.dot(segment);
+    if length_squared <= ::std::f32::EPSILON {
+        return start;
+    }
+
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+    start + segment * t
+}
+
+/// Finds the closest pair of points between two segments.
+///
+/// Based on the approach described in Real-Time Collision Detection, section 5.1.9.
+fn closest_points_segments(start_a: Point, end_a: Point, start_b: Point, end_b: Point) -> (Point, Point) {
+    let direction_a = end_a - start_a;
+    let direction_b = end_b - start_b;
+    let between = start_a - start_b;
+
+    let length_a = direction_a.dot(direction_a);
+    let length_b = direction_b.dot(direction_b);
+    let f = direction_b.dot(between);
+
+    if length_a <= ::std::f32::EPSILON && length_b <= ::std::f32::EPSILON {
+        return (start_a, start_b);
+    }
+
+    let (s, t) = if length_a <= ::std::f32::EPSILON {
+        (0.0, (f / length_b).clamp(0.0, 1.0))
+    } else {
+        let c = direction_a.dot(between);
+        if length_b <= ::std::f32::EPSILON {
+            ((-c / length_a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = direction_a.dot(direction_b);
+            let denominator = length_a * length_b - b * b;
+
+            let mut s = if denominator > ::std::f32::EPSILON {
+                ((b * f - c * length_b) / denominator).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            let mut t = (b * s + f) / length_b;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / length_a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / length_a).clamp(0.0, 1.0);
+            }
+
+            (s, t)
+        }
+    };
+
+    (start_a + direction_a * s, start_b + direction_b * t)
+}
+
+/// Raycasts against a sphere or capsule.
+fn raycast_round(origin: Point, direction: Vector3, start: Point, end: Point, radius: f32) -> Option<(f32, Vector3)> {
+    // Rays starting inside the shape don't report a hit.
+    if (origin - closest_point_on_segment(origin, start, end)).magnitude() <= radius {
+        return None;
+    }
+
+    let mut closest = raycast_sphere(origin, direction, start, radius);
+
+    let segment = end - start;
+    let length = segment.magnitude();
+    if length > ::std::f32::EPSILON {
+        let end_hit = raycast_sphere(origin, direction, end, radius);
+        closest = closer(closest, end_hit);
+
+        // Intersect with the infinite cylinder around the segment, then discard hits that land
+        // past either end cap.
+        let axis = segment / length;
+        let offset = origin - start;
+        let offset_perp = offset - axis * offset.dot(axis);
+        let direction_perp = direction - axis * direction.dot(axis);
+
+        let a = direction_perp.dot(direction_perp);
+        let b = offset_perp.dot(direction_perp);
+        let c = offset_perp.dot(offset_perp) - radius * radius;
+        let discriminant = b * b - a * c;
+
+        if a > ::std::f32::EPSILON && discriminant >= 0.0 {
+            let distance = (-b - discriminant.sqrt()) / a;
+            let along = (offset + direction * distance).dot(axis);
+            if distance >= 0.0 && along >= 0.0 && along <= length {
+                let normal = (offset_perp + direction_perp * distance) / radius;
+                closest = closer(closest, Some((distance, normal)));
+            }
+        }
+    }
+
+    closest
+}
+
+fn raycast_sphere(origin: Point, direction: Vector3, center: Point, radius: f32) -> Option<(f32, Vector3)> {
+    // Solve |offset + direction * t| = radius for t. Since direction is normalized the quadratic
+    // simplifies to t^2 + 2bt + c = 0.
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let c = offset.dot(offset) - radius * radius;
+
+    // Ray starts outside the sphere and points away from it.
+    if c > 0.0 && b > 0.0 {
+        return None;
+    }
+
+    let discriminant = b * b - c;
+    if discriminant < 0.0 || c <= 0.0 {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+    let normal = (offset + direction * distance).normalized();
+    Some((distance, normal))
+}
+
+fn raycast_box(origin: Point, direction: Vector3, bounds: &Bounds) -> Option<(f32, Vector3)> {
+    let offset = origin - bounds.center;
+
+    let mut t_min = 0.0;
+    let mut t_max = ::std::f32::MAX;
+    let mut hit_normal = None;
+
+    for axis in 0..3 {
+        let local_origin = offset.dot(bounds.axes[axis]);
+        let local_direction = direction.dot(bounds.axes[axis]);
+        let half_extent = bounds.half_extents[axis];
+
+        if local_direction.abs() < ::std::f32::EPSILON {
+            // Ray is parallel to this slab, so it either always or never overlaps it.
+            if local_origin.abs() > half_extent {
+                return None;
+            }
+            continue;
+        }
+
+        let inverse = 1.0 / local_direction;
+        let mut near = (-half_extent - local_origin) * inverse;
+        let mut far = (half_extent - local_origin) * inverse;
+        let mut sign = -1.0;
+        if near > far {
+            ::std::mem::swap(&mut near, &mut far);
+            sign = 1.0;
+        }
+
+        if near > t_min {
+            t_min = near;
+            hit_normal = Some(bounds.axes[axis] * sign);
+        }
+        t_max = t_max.min(far);
+
+        if t_min > t_max {
+            return None;
+        }
+    }
+
+    // If no slab moved `t_min` forward the ray started inside the box.
+    hit_normal.map(|normal| (t_min, normal))
+}
+
+fn raycast_plane(origin: Point, direction: Vector3, point: Point, normal: Vector3) -> Option<(f32, Vector3)> {
+    // Only rays approaching the front of the plane can hit it.
+    let height = (origin - point).dot(normal);
+    let approach = direction.dot(normal);
+    if height <= 0.0 || approach >= 0.0 {
+        return None;
+    }
+
+    Some((-height / approach, normal))
+}
+
+fn closer(first: Option<(f32, Vector3)>, second: Option<(f32, Vector3)>) -> Option<(f32, Vector3)> {
+    match (first, second) {
+        (Some(first), Some(second)) => if second.0 < first.0 { Some(second) } else { Some(first) },
+        (first, None) => first,
+        (None, second) => second,
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A set of `key = value` pairs loaded from a simple INI-style text file.
+///
+/// Blank lines and lines starting with `#` are ignored. Keys that appear after a `[section]`
+/// header are stored as `section.key`.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    values: HashMap<String, String>,
+    sections: Vec<String>,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Config> {
+        let mut text = String::new();
+        let mut file = File::open(path)?;
+        file.read_to_string(&mut text)?;
+
+        Ok(Config::parse(&*text))
+    }
+
+    pub fn parse(text: &str) -> Config {
+        let mut config = Config::default();
+        let mut section = String::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1 .. line.len() - 1].trim().into();
+                config.sections.push(section.clone());
+                continue;
+            }
+
+            // Silently skip malformed lines, the same as a missing key.
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => continue,
+            };
+
+            let key = if section.is_empty() { key.into() } else { format!("{}.{}", section, key) };
+            config.values.insert(key, value.into());
+        }
+
+        config
+    }
+
+    /// Gets the value for `key` parsed as `T`.
+    ///
+    /// Returns `None` if the key is missing or the value couldn't be parsed.
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.values.get(key).and_then(|value| value.parse().ok())
+    }
+
+    /// Gets the value for `key`, or `default` if the key is missing or couldn't be parsed.
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> T {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// Gets the names of all sections in the order they appear in the file.
+    pub fn sections(&self) -> &[String] {
+        &*self.sections
+    }
+}
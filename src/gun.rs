@@ -1,11 +1,71 @@
-use physics::Rigidbody;
+use bore::{Bore, Firing};
+use bullet::BulletPool;
+use cartridge::CartridgeSpec;
+use collision::{Collider, ColliderId, CollisionWorld, LAYER_DEBRIS, LAYER_WORLD, Shape};
+use physics::{GRAVITY, InertiaTensor, Integrator, Rigidbody};
+use recoil::Recoil;
 use gunship::*;
 use gunship::math::*;
 use gunship::mesh_renderer::MeshRenderer;
 use gunship::resource::Mesh;
 use gunship::transform::Transform;
-use std::sync::Arc;
-use tween;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use tween::{self, AngleTween};
+use weapon::{ActionEvent, Weapon};
+
+/// The angle (in radians) the hammer is drawn back to at half-cock.
+const HAMMER_HALF_COCK_ANGLE: f32 = 0.3;
+
+/// The angle (in radians) the hammer is drawn back to at full cock.
+const HAMMER_FULL_COCK_ANGLE: f32 = 0.7;
+
+/// How long (in seconds) it takes the player to draw the hammer from down to full cock.
+const HAMMER_COCK_TIME: f32 = 0.25;
+
+/// How long (in seconds) it takes the hammer to fall when the trigger is pulled.
+const HAMMER_FALL_TIME: f32 = 0.03;
+
+/// How long (in seconds) it takes the hammer to return when lowered by hand.
+const HAMMER_RETURN_TIME: f32 = 0.12;
+
+/// The angle (in radians) the cylinder swings out to when opened.
+const CYLINDER_SWING_ANGLE: f32 = 1.4;
+
+/// How long (in seconds) it takes the cylinder to swing open or closed.
+const CYLINDER_SWING_TIME: f32 = 0.2;
+
+/// How long (in seconds) ejected casings stay in the world before despawning.
+const CASING_LIFETIME: f32 = 10.0;
+
+/// The speed (in meters per second) at which the ejector rod throws cartridges out of the cylinder.
+const EJECT_SPEED: f32 = 1.5;
+
+/// How long (in seconds) it takes to bring the revolver up close for inspection.
+const INSPECT_TIME: f32 = 0.3;
+
+/// How far (in meters) spent cases are drawn sticking out the back of the cylinder while
+/// inspecting, so that they can be told apart from live rounds at a glance.
+const SPENT_SETBACK: f32 = 0.004;
+
+/// What's in one of the cylinder's chambers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChamberState {
+    Empty,
+    Live,
+    Spent,
+}
+
+impl ChamberState {
+    /// Gets the state of a chamber holding `chamber`.
+    pub fn of(chamber: &Option<Cartridge>) -> ChamberState {
+        match *chamber {
+            None => ChamberState::Empty,
+            Some(ref cartridge) if cartridge.has_fired() => ChamberState::Spent,
+            Some(_) => ChamberState::Live,
+        }
+    }
+}
 
 /// Represents the cylinder of a revolver, tracking the contents of each cylinder.
 #[derive(Debug)]
@@ -49,20 +109,73 @@ impl Cylinder {
         &mut self.cylinders[self.position]
     }
 
+    /// Gets what's in chamber `index`, counting from chamber 0 rather than the hammer.
+    pub fn chamber_state(&self, index: usize) -> ChamberState {
+        ChamberState::of(&self.cylinders[index])
+    }
+
+    /// Gets what's in every chamber, starting with the one under the hammer and going round in
+    /// the direction the cylinder turns when cocked.
+    pub fn chamber_states(&self) -> Vec<ChamberState> {
+        let capacity = self.capacity();
+        (0..capacity)
+            .map(|offset| self.chamber_state((self.position + offset) % capacity))
+            .collect()
+    }
+
     /// Gets the number of cartidges the cylinder can hold.
     pub fn capacity(&self) -> usize {
         self.cylinders.len()
     }
+
+    /// Removes all cartridges from the cylinder, leaving every chamber empty.
+    pub fn take_all(&mut self) -> Vec<Cartridge> {
+        self.cylinders.iter_mut().filter_map(|chamber| chamber.take()).collect()
+    }
+}
+
+/// The position of a revolver's hammer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HammerState {
+    /// The hammer is resting against the frame. Pulling the trigger fires double-action.
+    Down,
+
+    /// The hammer is partially drawn back and the trigger is blocked.
+    HalfCock,
+
+    /// The hammer is fully drawn back and pulling the trigger fires single-action.
+    FullCock,
+}
+
+impl HammerState {
+    /// Gets the angle the hammer rests at in this state.
+    fn angle(self) -> f32 {
+        match self {
+            HammerState::Down => 0.0,
+            HammerState::HalfCock => HAMMER_HALF_COCK_ANGLE,
+            HammerState::FullCock => HAMMER_FULL_COCK_ANGLE,
+        }
+    }
 }
 
+/// Animates the cylinder catching up to its logical position.
+///
+/// The cylinder's position is updated as soon as a rotation is requested, so the tween only
+/// tracks how far behind the visible cylinder is.
 #[derive(Debug, Clone, Copy)]
 struct CylinderTween {
     time: f32,
     target_time: f32,
-    end_pos: usize,
 
-    /// Either -1 or 1 to specify the direction of rotation.
-    direction: f32,
+    /// How many chambers behind the visible cylinder was when the tween started.
+    start_offset: f32,
+}
+
+impl CylinderTween {
+    /// Gets how many chambers behind the visible cylinder currently is.
+    fn offset(&self) -> f32 {
+        self.start_offset * (1.0 - tween::ease_out_back(self.time / self.target_time))
+    }
 }
 
 #[derive(Debug)]
@@ -70,28 +183,52 @@ pub struct Revolver {
     pub transform: Transform,
     pub mesh_renderer: MeshRenderer,
     pub rigidbody: Rigidbody,
+    pub recoil: Recoil,
+
+    /// The chamberings of the cartridges the gun can be loaded with.
+    pub chamberings: Vec<String>,
 
     pub hammer_transform: Transform,
     pub hammer_renderer: MeshRenderer,
     hammer_offset: Vector3,
     hammer_pivot: Vector3,
 
+    /// The current angle of the hammer around its pivot, where 0 is resting against the frame.
+    hammer_angle: f32,
+    hammer_tween: Option<AngleTween>,
+
     cylinder: Cylinder,
     cylinder_offset: Vector3,
     cylinder_radius: f32,
     cylinder_tween: Option<CylinderTween>,
 
-    bullet_offset: Vector3, // TODO: Configure based on gun mesh.
-    is_cocked: bool,
+    /// The point (relative to the gun) the cylinder swings out around.
+    crane_pivot: Vector3,
+    cylinder_open: bool,
+    swing_angle: f32,
+    swing_tween: Option<AngleTween>,
+
+    pub bore: Bore,
+
+    /// The chamber that was under the hammer when a hang-fire was struck.
+    hang_fire_chamber: usize,
+
+    /// Whether the player is holding the revolver up to look over the cylinder, and how far it's
+    /// been brought up, from 0 in the hand to 1 right in front of the player's face.
+    inspecting: bool,
+    inspection: f32,
 
-    bullet_mesh: Arc<Mesh>,
+    hammer: HammerState,
+    events: Vec<ActionEvent>,
+
+    bullets: Arc<Mutex<BulletPool>>,
 }
 
 impl Revolver {
     pub fn new(
         mesh: &Mesh,
         hammer_mesh: &Mesh,
-        bullet_mesh: Arc<Mesh>,
+        bullets: Arc<Mutex<BulletPool>>,
         start_pos: Point,
         start_orientation: Orientation,
     ) -> Revolver {
@@ -99,7 +236,16 @@ impl Revolver {
         transform.set_position(start_pos);
         transform.set_orientation(start_orientation);
         let mesh_renderer = MeshRenderer::new(&mesh, &transform);
-        let rigidbody = Rigidbody::new();
+        let mut rigidbody = Rigidbody::new();
+        rigidbody.integrator = Integrator::VelocityVerlet;
+
+        // Approximate the gun as a solid box roughly the size of the mesh.
+        rigidbody.mass = 1.0;
+        rigidbody.inertia = InertiaTensor::from_shape(
+            &Shape::Obb { half_extents: Vector3::new(0.015, 0.06, 0.1) },
+            rigidbody.mass,
+        );
+        rigidbody.teleport(start_pos, start_orientation);
 
         let mut hammer_transform = Transform::new();
         hammer_transform.set_position(start_pos + Vector3::new(0.0, 0.05, 0.05));
@@ -110,89 +256,320 @@ impl Revolver {
             transform: transform,
             mesh_renderer: mesh_renderer,
             rigidbody: rigidbody,
+            recoil: Recoil::default(),
+
+            // A .357 Magnum revolver can also fire the shorter .38 Special.
+            chamberings: vec!["357_magnum".into(), "38_special".into()],
 
             hammer_transform: hammer_transform,
             hammer_renderer: hammer_renderer,
             hammer_offset: Vector3::new(0.0, 0.05, 0.05),
             hammer_pivot: Vector3::new(0.0, -0.025, -0.025),
+            hammer_angle: 0.0,
+            hammer_tween: None,
 
             cylinder: Cylinder::new(6),
             cylinder_offset: Vector3::new(0.0, 0.05, 0.0),
             cylinder_radius: 0.03,
             cylinder_tween: None,
 
-            bullet_offset: Vector3::new(0.0, 0.04, 0.2),
-            is_cocked: false,
+            crane_pivot: Vector3::new(-0.015, 0.03, 0.0),
+            cylinder_open: false,
+            swing_angle: 0.0,
+            swing_tween: None,
 
-            bullet_mesh: bullet_mesh,
+            // TODO: Configure based on gun mesh.
+            bore: Bore::new(Vector3::new(0.0, 0.04, 0.2)),
+            hang_fire_chamber: 0,
+
+            inspecting: false,
+            inspection: 0.0,
+
+            hammer: HammerState::Down,
+            events: Vec::new(),
+
+            bullets: bullets,
         }
     }
 
-    /// Tries to fire the gun. Returns `true` if the gun fired, `false` otherwise.
-    pub fn fire(&mut self) -> bool {
-        // If the hammer isn't cocked we can't fire, so do nothing.
-        if !self.is_cocked {
-            return false;
+    /// Gets the current position of the hammer.
+    pub fn hammer(&self) -> HammerState {
+        self.hammer
+    }
+
+    /// Takes all action events that have happened since the last call.
+    pub fn drain_events(&mut self) -> Vec<ActionEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Gets what's in every chamber, starting with the one under the hammer.
+    pub fn chamber_states(&self) -> Vec<ChamberState> {
+        self.cylinder.chamber_states()
+    }
+
+    /// Gets what's in the chamber under the hammer.
+    pub fn current_chamber_state(&self) -> ChamberState {
+        ChamberState::of(self.cylinder.current())
+    }
+
+    /// Brings the revolver up close to look over the cylinder, or lowers it again.
+    ///
+    /// Call this every frame with whether or not the player is inspecting the gun. The cylinder
+    /// can be turned by hand with `rotate_cylinder()` while inspecting, but the trigger is
+    /// blocked.
+    pub fn inspect(&mut self, inspecting: bool) {
+        self.inspecting = inspecting;
+
+        let step = time::delta_f32() / INSPECT_TIME;
+        let inspection = if inspecting { self.inspection + step } else { self.inspection - step };
+        self.inspection = inspection.clamp(0.0, 1.0);
+    }
+
+    pub fn is_inspecting(&self) -> bool {
+        self.inspecting
+    }
+
+    /// Gets how far the revolver has been brought up for inspection, eased so that it can be
+    /// used to blend the gun's position.
+    pub fn inspection(&self) -> f32 {
+        tween::ease_out_quad(self.inspection)
+    }
+
+    /// Pulls the trigger.
+    ///
+    /// If the hammer is cocked it falls immediately (single-action). If the hammer is down the
+    /// trigger pull cocks it first (double-action). At half-cock the trigger is blocked.
+    pub fn pull_trigger(&mut self) {
+        // The hammer can't reach the cartridges while the cylinder is swung out, and the player's
+        // finger is off the trigger while they're looking the gun over. The trigger also
+        // stays blocked until a hang-fire goes off or is ejected.
+        if self.cylinder_open || self.inspecting || self.bore.is_hanging_fire() {
+            self.events.push(ActionEvent::TriggerBlocked);
+            return;
+        }
+
+        match self.hammer {
+            HammerState::HalfCock => {
+                self.events.push(ActionEvent::TriggerBlocked);
+            },
+
+            HammerState::Down => {
+                self.cock();
+                self.drop_hammer();
+            },
+
+            HammerState::FullCock => {
+                self.drop_hammer();
+            },
+        }
+    }
+
+    /// Draws the hammer back a little further, as if the player was holding it with their thumb.
+    ///
+    /// Call this every frame while the player is holding the hammer. Once the hammer has been
+    /// drawn all the way back it locks at full cock.
+    pub fn draw_hammer(&mut self) {
+        if self.hammer == HammerState::FullCock || self.cylinder_open {
+            return;
+        }
+
+        self.hammer_tween = None;
+        self.hammer_angle += HAMMER_FULL_COCK_ANGLE / HAMMER_COCK_TIME * time::delta_f32();
+
+        if self.hammer_angle >= HAMMER_FULL_COCK_ANGLE {
+            self.cock();
         }
+    }
+
+    /// Lets go of a partially drawn hammer, easing it back to where it was resting.
+    ///
+    /// Does nothing if the hammer isn't partially drawn, so it's safe to call every frame the
+    /// player isn't holding the hammer.
+    pub fn release_hammer(&mut self) {
+        let rest_angle = self.hammer.angle();
+        if self.hammer_tween.is_none() && self.hammer_angle != rest_angle {
+            self.tween_hammer(rest_angle, HAMMER_RETURN_TIME, tween::ease_out_quad);
+        }
+    }
 
-        // TODO: Animate hammer falling.
-        self.is_cocked = false;
+    /// Draws the hammer back to half-cock.
+    pub fn half_cock(&mut self) {
+        if self.hammer == HammerState::Down && !self.cylinder_open {
+            self.hammer = HammerState::HalfCock;
+            self.tween_hammer(HAMMER_HALF_COCK_ANGLE, HAMMER_RETURN_TIME, tween::ease_out_quad);
+            self.events.push(ActionEvent::HalfCocked);
+        }
+    }
 
-        if let Some(cartridge) = self.cylinder.current_mut().as_mut() {
-            if !cartridge.has_fired {
-                // TODO: Play audio on gunshot.
-                // let mut audio_source = audio_manager.get_mut(player.gun_entity).unwrap();
-                // audio_source.reset();
-                // audio_source.play();
+    /// Lowers the hammer from half or full cock without firing.
+    pub fn decock(&mut self) {
+        if self.hammer != HammerState::Down {
+            self.hammer = HammerState::Down;
+            self.tween_hammer(0.0, HAMMER_RETURN_TIME, tween::ease_out_quad);
+            self.events.push(ActionEvent::Decocked);
+        }
+    }
 
-                let bullet_pos = self.transform.position()
-                               + (self.bullet_offset.x * self.transform.right())
-                               + (self.bullet_offset.y * self.transform.up())
-                               + (self.bullet_offset.z * self.transform.forward());
-                let mut bullet = Bullet::new(&self.bullet_mesh, bullet_pos, self.transform.orientation());
-                engine::run_each_frame(move || {
-                    bullet.update();
-                });
+    fn cock(&mut self) {
+        self.hammer = HammerState::FullCock;
+        self.hammer_angle = HAMMER_FULL_COCK_ANGLE;
+        self.hammer_tween = None;
+        self.rotate_cylinder(1);
+        self.events.push(ActionEvent::Cocked);
+    }
 
-                // Empty the chartridge.
-                cartridge.has_fired = true;
+    /// Starts the hammer moving from its current angle to `end_angle`.
+    fn tween_hammer(&mut self, end_angle: f32, target_time: f32, easing: fn(f32) -> f32) {
+        self.hammer_tween = Some(AngleTween::new(self.hammer_angle, end_angle, target_time, easing));
+    }
 
-                // TODO: Change cartridge mesh to empty cartridge.
-                let scale = cartridge.transform.scale().set_z(0.001);
-                cartridge.transform.set_scale(scale);
+    pub fn is_cylinder_open(&self) -> bool {
+        self.cylinder_open
+    }
 
-                return true;
+    /// Swings the cylinder out of the frame so that it can be emptied.
+    ///
+    /// The cylinder can only be opened while the hammer is down. While open the gun can't be
+    /// cocked or fired.
+    pub fn open_cylinder(&mut self) {
+        if !self.cylinder_open && self.hammer == HammerState::Down {
+            self.cylinder_open = true;
+            self.swing_tween = Some(AngleTween::new(self.swing_angle, CYLINDER_SWING_ANGLE, CYLINDER_SWING_TIME, tween::ease_out_quad));
+            self.events.push(ActionEvent::ActionOpened);
+        }
+    }
+
+    /// Swings the cylinder back into the frame.
+    pub fn close_cylinder(&mut self) {
+        if self.cylinder_open {
+            self.cylinder_open = false;
+            self.swing_tween = Some(AngleTween::new(self.swing_angle, 0.0, CYLINDER_SWING_TIME, tween::ease_out_back));
+            self.events.push(ActionEvent::ActionClosed);
+        }
+    }
+
+    /// Pushes the ejector rod, removing every cartridge from the cylinder.
+    ///
+    /// Returns nothing if the cylinder isn't open.
+    pub fn eject_cartridges(&mut self) -> Vec<Cartridge> {
+        if !self.cylinder_open {
+            return Vec::new();
+        }
+
+        if let Some(cartridge) = self.cylinder.cylinders[self.hang_fire_chamber].as_mut() {
+            self.bore.extract(cartridge);
+        }
+
+        let cartridges = self.cylinder.take_all();
+        self.events.push(ActionEvent::CartridgesEjected(cartridges.len()));
+        cartridges
+    }
+
+    /// Pulls the cartridge out of the current chamber by hand.
+    ///
+    /// Returns `None` if the chamber is empty or the cylinder isn't open.
+    pub fn pull_cartridge(&mut self) -> Option<Cartridge> {
+        if !self.cylinder_open {
+            return None;
+        }
+
+        let mut cartridge = self.cylinder.current_mut().take();
+        if self.cylinder.position == self.hang_fire_chamber {
+            if let Some(cartridge) = cartridge.as_mut() {
+                self.bore.extract(cartridge);
             }
         }
 
-        false
+        cartridge
     }
 
-    pub fn pull_hammer(&mut self) {
-        if !self.is_cocked {
-            // TODO: Animate hammer pulling back.
-            self.is_cocked = true;
+    /// Gets the speed and direction cartridges are thrown when ejected, in world space.
+    pub fn eject_velocity(&self) -> Vector3 {
+        // The ejector rod pushes cartridges out the back of the cylinder.
+        self.rigidbody.velocity() + self.transform.orientation() * Vector3::new(0.0, 0.0, EJECT_SPEED)
+    }
 
-            self.rotate_cylinder(1);
+    /// Lets the hammer fall, firing the cartridge under the hammer if it's live.
+    fn drop_hammer(&mut self) {
+        // The hammer is spring driven, so it accelerates all the way down.
+        self.hammer = HammerState::Down;
+        self.tween_hammer(0.0, HAMMER_FALL_TIME, tween::ease_in_quad);
+
+        let event = match self.cylinder.current_mut().as_mut() {
+            Some(cartridge) => {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                self.bore.strike(cartridge, &mut firing)
+            },
+            None => ActionEvent::DryFired,
+        };
+
+        if let ActionEvent::HangFire = event {
+            self.hang_fire_chamber = self.cylinder.position;
         }
+
+        self.events.push(event);
     }
 
-    pub fn rotate_cylinder(&mut self, rotation: isize) {
-        if let Some(tween) = self.cylinder_tween {
-            // TODO: What should we do when the cylinder is already rotating?
-        } else {
-            let pos = self.cylinder.position as isize + rotation;
+    /// Counts down a pending hang-fire, firing the cartridge once it goes off.
+    ///
+    /// The cartridge only fires down the barrel if it's still under the hammer with the cylinder
+    /// closed, otherwise it just burns out in its chamber.
+    pub fn update(&mut self, delta: f32) {
+        if !self.bore.update(delta) {
+            return;
+        }
+
+        let aligned = self.cylinder.position == self.hang_fire_chamber && !self.cylinder_open;
+        if let Some(cartridge) = self.cylinder.cylinders[self.hang_fire_chamber].as_mut() {
+            if aligned {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                let event = self.bore.discharge(cartridge, &mut firing);
+                self.events.push(event);
+            } else {
+                cartridge.fire();
+            }
+        }
+    }
 
-            self.cylinder_tween = Some(CylinderTween {
-                time: 0.0,
-                target_time: 0.2,
-                end_pos: pos.modulo(self.cylinder.capacity() as isize) as usize,
-                direction: rotation as f32 / (rotation as f32).abs(),
-            });
+    /// Knocks a stuck bullet out of the barrel with a rod, which can only be done with the
+    /// cylinder out of the way.
+    pub fn clear_bore(&mut self) {
+        if self.cylinder_open && self.bore.clear() {
+            self.events.push(ActionEvent::BoreCleared);
+        }
+    }
+
+    /// Rotates the cylinder by the specified number of chambers.
+    ///
+    /// The cylinder's position changes immediately and the visible cylinder animates to catch up.
+    /// If the cylinder is already rotating the new rotation is added on top, so quick successive
+    /// rotations accumulate into a single longer spin rather than being dropped.
+    pub fn rotate_cylinder(&mut self, rotation: isize) {
+        if rotation == 0 {
+            return;
         }
+
+        let capacity = self.cylinder.capacity() as isize;
+        let pos = self.cylinder.position as isize + rotation;
+        self.cylinder.position = pos.modulo(capacity) as usize;
+
+        // Start from wherever the visible cylinder currently is so that it doesn't snap.
+        let current_offset = self.cylinder_tween.map_or(0.0, |tween| tween.offset());
+        let start_offset = current_offset + rotation as f32;
+
+        // Longer spins take a bit longer, but not proportionally so.
+        self.cylinder_tween = Some(CylinderTween {
+            time: 0.0,
+            target_time: 0.2 * start_offset.abs().max(1.0).sqrt(),
+            start_offset: start_offset,
+        });
     }
 
     pub fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if !cartridge.spec.fits(&self.chamberings) {
+            return Err(cartridge);
+        }
+
         // TODO: Animate loading the cartridge.
         let cylinder = self.cylinder.current_mut();
         match cylinder {
@@ -204,87 +581,336 @@ impl Revolver {
         }
     }
 
+    /// Drops a speedloader's worth of cartridges into every empty chamber at once, going round
+    /// from the chamber under the hammer.
+    ///
+    /// Only possible while the cylinder is swung out. Returns any cartridges that don't fit the
+    /// gun or didn't find an empty chamber, which stay in the speedloader.
+    pub fn load_clip(&mut self, cartridges: Vec<Cartridge>) -> Vec<Cartridge> {
+        if !self.cylinder_open {
+            return cartridges;
+        }
+
+        let (mut cartridges, mut remaining): (Vec<_>, Vec<_>) = cartridges
+            .into_iter()
+            .partition(|cartridge| cartridge.spec.fits(&self.chamberings));
+        cartridges.reverse();
+
+        // TODO: Animate the speedloader.
+        let capacity = self.cylinder.capacity();
+        for offset in 0..capacity {
+            let index = (self.cylinder.position + offset) % capacity;
+            let chamber = &mut self.cylinder.cylinders[index];
+            if chamber.is_none() {
+                *chamber = cartridges.pop();
+            }
+        }
+
+        remaining.extend(cartridges.into_iter().rev());
+        remaining
+    }
+
     pub fn update_transforms(&mut self) {
         let tween_offset = if let Some(mut tween) = self.cylinder_tween {
             // Update tween time.
             tween.time += time::delta_f32();
 
             if tween.time > tween.target_time {
-                // Tween is done. We want to set the cylinder's position to the end position.
-                self.cylinder.position = tween.end_pos;
+                // Tween is done, the visible cylinder has caught up with its position.
                 self.cylinder_tween = None;
                 0.0
             } else {
                 self.cylinder_tween = Some(tween);
-                tween::ease_out_back(tween.time / tween.target_time) * tween.direction
+                tween.offset()
             }
         } else {
             0.0
         };
 
+        if let Some(mut tween) = self.swing_tween {
+            match tween.advance(time::delta_f32()) {
+                Some(angle) => {
+                    self.swing_angle = angle;
+                    self.swing_tween = Some(tween);
+                },
+                None => {
+                    self.swing_angle = tween.end_angle();
+                    self.swing_tween = None;
+                },
+            }
+        }
+
+        // Swing the cylinder out around the crane's pivot, which runs parallel to the barrel.
+        let swing_rotation = Orientation::from_eulers(0.0, 0.0, self.swing_angle);
+        let local_center = self.crane_pivot + swing_rotation * (self.cylinder_offset - self.crane_pivot);
+
         let capacity = self.cylinder.capacity();
         let cylinder_position = self.cylinder.position;
-        let oriented_offset = self.transform.orientation() * self.cylinder_offset;
+        let oriented_offset = self.transform.orientation() * local_center;
         let cylinder_center = self.transform.position() + oriented_offset;
+        let setback = self.transform.orientation() * Vector3::new(0.0, 0.0, SPENT_SETBACK * self.inspection());
 
         for (index, cylinder) in self.cylinder.cylinders.iter_mut().enumerate() {
             if let Some(cartridge) = cylinder.as_mut() {
                 let pos = (index as isize - cylinder_position as isize).modulo(capacity as isize);
 
-                let rotation = TAU / capacity as f32 * (pos as f32 - tween_offset);
+                let rotation = TAU / capacity as f32 * (pos as f32 + tween_offset) + self.swing_angle;
                 let local_orientation = Orientation::from_eulers(0.0, 0.0, rotation);
 
                 let orientation = self.transform.orientation() + local_orientation;
-                let cartridge_up_offset = orientation.up() * self.cylinder_radius;
+                let mut cartridge_offset = orientation.up() * self.cylinder_radius;
+                if cartridge.has_fired() {
+                    cartridge_offset = cartridge_offset + setback;
+                }
 
                 cartridge.transform.set_orientation(orientation);
-                cartridge.transform.set_position(cylinder_center + cartridge_up_offset);
+                cartridge.transform.set_position(cylinder_center + cartridge_offset);
+            }
+        }
+
+        if let Some(mut tween) = self.hammer_tween {
+            match tween.advance(time::delta_f32()) {
+                Some(angle) => {
+                    self.hammer_angle = angle;
+                    self.hammer_tween = Some(tween);
+                },
+                None => {
+                    self.hammer_angle = tween.end_angle();
+                    self.hammer_tween = None;
+                },
             }
         }
 
-        let hammer_position = self.transform.position() + self.transform.orientation() * self.hammer_offset;
+        // Rotate the hammer around its pivot, which is offset from the hammer's origin.
+        let hammer_rotation = Orientation::from_eulers(self.hammer_angle, 0.0, 0.0);
+        let local_position = self.hammer_offset + self.hammer_pivot - hammer_rotation * self.hammer_pivot;
+        let hammer_position = self.transform.position() + self.transform.orientation() * local_position;
         self.hammer_transform.set_position(hammer_position);
-        self.hammer_transform.set_orientation(self.transform.orientation());
+        self.hammer_transform.set_orientation(self.transform.orientation() + hammer_rotation);
     }
 }
 
+impl Weapon for Revolver {
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn rigidbody(&self) -> &Rigidbody {
+        &self.rigidbody
+    }
+
+    fn rigidbody_mut(&mut self) -> &mut Rigidbody {
+        &mut self.rigidbody
+    }
+
+    fn recoil(&self) -> &Recoil {
+        &self.recoil
+    }
+
+    fn pull_trigger(&mut self) {
+        Revolver::pull_trigger(self);
+    }
+
+    /// Thumbs the hammer back for as long as the button is held.
+    fn secondary_action(&mut self, held: bool) {
+        if held {
+            self.draw_hammer();
+        } else {
+            self.release_hammer();
+        }
+    }
+
+    fn safety(&mut self) {
+        self.half_cock();
+    }
+
+    fn decock(&mut self) {
+        Revolver::decock(self);
+    }
+
+    fn is_action_open(&self) -> bool {
+        self.is_cylinder_open()
+    }
+
+    fn open_action(&mut self) {
+        self.open_cylinder();
+    }
+
+    fn close_action(&mut self) {
+        self.close_cylinder();
+    }
+
+    fn cycle(&mut self, amount: isize) {
+        self.rotate_cylinder(amount);
+    }
+
+    fn accepts(&self, spec: &CartridgeSpec) -> bool {
+        spec.fits(&self.chamberings)
+    }
+
+    fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        Revolver::load_cartridge(self, cartridge)
+    }
+
+    fn load_clip(&mut self, cartridges: Vec<Cartridge>) -> Vec<Cartridge> {
+        Revolver::load_clip(self, cartridges)
+    }
+
+    fn unload(&mut self) -> Vec<Cartridge> {
+        self.eject_cartridges()
+    }
+
+    fn unload_one(&mut self) -> Option<Cartridge> {
+        self.pull_cartridge()
+    }
+
+    fn eject_velocity(&self) -> Vector3 {
+        Revolver::eject_velocity(self)
+    }
+
+    fn clear_bore(&mut self) {
+        Revolver::clear_bore(self);
+    }
+
+    fn inspect(&mut self, inspecting: bool) {
+        Revolver::inspect(self, inspecting);
+    }
+
+    fn inspection(&self) -> f32 {
+        Revolver::inspection(self)
+    }
+
+    fn update(&mut self, delta: f32) {
+        Revolver::update(self, delta);
+    }
+
+    fn drain_events(&mut self) -> Vec<ActionEvent> {
+        Revolver::drain_events(self)
+    }
+
+    fn interpolate(&mut self, alpha: f32) {
+        self.rigidbody.interpolate(&mut self.transform, alpha);
+    }
+
+    fn update_transforms(&mut self) {
+        Revolver::update_transforms(self);
+    }
+}
+
+/// The meshes used to draw a cartridge before and after it's been fired.
+#[derive(Debug, Clone)]
+pub struct CartridgeMeshes {
+    pub live: Arc<Mesh>,
+    pub spent: Arc<Mesh>,
+}
+
 /// Tracks state for the bullet cartridge when it's in the gun or the player's inventory.
 #[derive(Debug)]
 pub struct Cartridge {
     pub transform: Transform,
     pub mesh_renderer: MeshRenderer,
+    pub spec: Arc<CartridgeSpec>,
+
+    meshes: CartridgeMeshes,
+    has_fired: bool,
+}
+
+impl Cartridge {
+    /// Creates a new live cartridge of the specified type.
+    pub fn new(meshes: &CartridgeMeshes, spec: Arc<CartridgeSpec>) -> Cartridge {
+        let transform = Transform::new();
+        let mesh_renderer = MeshRenderer::new(&meshes.live, &transform);
+
+        Cartridge {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+            spec: spec,
 
-    pub has_fired: bool,
+            meshes: meshes.clone(),
+            has_fired: false,
+        }
+    }
+
+    pub fn has_fired(&self) -> bool {
+        self.has_fired
+    }
+
+    /// Marks the cartridge as spent, swapping it over to the spent casing mesh.
+    pub fn fire(&mut self) {
+        self.has_fired = true;
+        self.mesh_renderer = MeshRenderer::new(&self.meshes.spent, &self.transform);
+
+        // TODO: Remove this once we have a proper spent casing mesh.
+        self.transform.set_scale(Vector3::new(0.008, 0.008, 0.012));
+    }
 }
 
-/// Tracks state for a bullet that's been fired.
+/// A cartridge that's been ejected from a gun and is falling freely.
+///
+/// Casings bounce off of static colliders and despawn after `CASING_LIFETIME` seconds.
 #[derive(Debug)]
-pub struct Bullet {
-    transform: Transform,
-    mesh_renderer: MeshRenderer,
-    pub speed: f32,
+pub struct Casing {
+    pub cartridge: Cartridge,
+    pub rigidbody: Rigidbody,
+    pub collider: ColliderId,
+
+    lifetime: f32,
 }
 
-impl Bullet {
-    pub fn new(mesh: &Mesh, position: Point, orientation: Orientation) -> Bullet {
-        let mut transform = Transform::new();
-        transform.set_position(position);
-        transform.set_orientation(orientation);
+impl Casing {
+    /// Releases `cartridge` into the world at its current position, moving at `velocity`.
+    pub fn new(cartridge: Cartridge, velocity: Vector3, collision_world: &mut CollisionWorld) -> Casing {
+        let mut rigidbody = Rigidbody::new();
+        rigidbody.mass = 0.015;
+        rigidbody.inertia = InertiaTensor::from_shape(
+            &Shape::Obb { half_extents: Vector3::new(0.0045, 0.0045, 0.015) },
+            rigidbody.mass,
+        );
+        rigidbody.teleport(cartridge.transform.position(), cartridge.transform.orientation());
+        rigidbody.set_velocity(velocity);
+
+        // Casings only bounce off the level, they'd just get in the way if they hit anything else.
+        let collider = collision_world.add(Collider {
+            restitution: 0.4,
+            friction: 0.6,
+            layer: LAYER_DEBRIS,
+            mask: LAYER_WORLD,
+            .. Collider::dynamic(Shape::Sphere { radius: 0.006 }, rigidbody.position())
+        });
+
+        Casing {
+            cartridge: cartridge,
+            rigidbody: rigidbody,
+            collider: collider,
 
-        // TODO: Remove this once we have a proper bullet mesh.
-        transform.set_scale(Vector3::new(0.1, 0.1, 1.0));
+            lifetime: 0.0,
+        }
+    }
 
-        let mesh_renderer = MeshRenderer::new(mesh, &transform);
+    /// Checks if the casing has been around long enough that it should be despawned.
+    pub fn is_expired(&self) -> bool {
+        self.lifetime >= CASING_LIFETIME
+    }
 
-        Bullet {
-            transform: transform,
-            mesh_renderer: mesh_renderer,
-            speed: 100.0
+    pub fn step(&mut self, delta: f32) {
+        let gravity = Vector3::down() * GRAVITY * self.rigidbody.mass;
+        self.rigidbody.apply_force(gravity);
+        self.rigidbody.step(delta);
+
+        self.lifetime += delta;
+    }
+
+    /// Bounces the casing off of anything it hit during the last step.
+    ///
+    /// The casing's collider must have been synced and the collision world stepped since the
+    /// casing was last stepped.
+    pub fn resolve_contacts(&mut self, collision_world: &CollisionWorld) {
+        for contact in collision_world.contacts_with(self.collider) {
+            self.rigidbody.resolve_contact(&contact);
         }
     }
 
-    pub fn update(&mut self) {
-        let forward = self.transform.forward();
-        self.transform.translate(forward * self.speed * time::delta_f32());
+    pub fn interpolate(&mut self, alpha: f32) {
+        self.rigidbody.interpolate(&mut self.cartridge.transform, alpha);
     }
 }
@@ -0,0 +1,273 @@
+use cartridge::CartridgeSpec;
+use gun::{Cartridge, CartridgeMeshes};
+use gunship::math::*;
+use gunship::mesh_renderer::MeshRenderer;
+use gunship::resource::Mesh;
+use gunship::transform::Transform;
+use std::sync::Arc;
+
+/// A pouch of loose rounds.
+///
+/// Rounds in a pouch are only tracked by type, they don't become `Cartridge`s until they're taken
+/// out.
+#[derive(Debug, Clone)]
+pub struct Pouch {
+    rounds: Vec<Arc<CartridgeSpec>>,
+    capacity: usize,
+}
+
+impl Pouch {
+    /// Creates a new, empty pouch that holds up to `capacity` rounds.
+    pub fn new(capacity: usize) -> Pouch {
+        Pouch {
+            rounds: Vec::with_capacity(capacity),
+            capacity: capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.rounds.len() >= self.capacity
+    }
+
+    /// Counts the rounds of the named type in the pouch.
+    pub fn count(&self, name: &str) -> usize {
+        self.rounds.iter().filter(|spec| spec.name == name).count()
+    }
+
+    /// Drops a round into the pouch, or gives it back if the pouch is full.
+    pub fn put(&mut self, spec: Arc<CartridgeSpec>) -> Result<(), Arc<CartridgeSpec>> {
+        if self.is_full() {
+            return Err(spec);
+        }
+
+        self.rounds.push(spec);
+        Ok(())
+    }
+
+    /// Takes a round of the named type out of the pouch.
+    pub fn take(&mut self, name: &str) -> Option<Arc<CartridgeSpec>> {
+        let index = self.rounds.iter().rposition(|spec| spec.name == name)?;
+        Some(self.rounds.swap_remove(index))
+    }
+}
+
+/// Holds a full load of cartridges ready to go into the gun in one motion, e.g. a revolver
+/// speedloader or a rifle's stripper clip.
+#[derive(Debug, Clone)]
+pub struct Speedloader {
+    rounds: Vec<Arc<CartridgeSpec>>,
+    capacity: usize,
+}
+
+impl Speedloader {
+    /// Creates a new, empty speedloader that holds up to `capacity` rounds.
+    pub fn new(capacity: usize) -> Speedloader {
+        Speedloader {
+            rounds: Vec::with_capacity(capacity),
+            capacity: capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.rounds.len() >= self.capacity
+    }
+}
+
+/// Everything the player is carrying to load their guns with.
+///
+/// Loose rounds are kept in pouches, and speedloaders can be filled from them ahead of time.
+/// Only live rounds can be stored, spent casings are left on the ground.
+#[derive(Debug)]
+pub struct Inventory {
+    pub pouches: Vec<Pouch>,
+    pub speedloaders: Vec<Speedloader>,
+
+    meshes: CartridgeMeshes,
+}
+
+impl Inventory {
+    /// Creates an inventory with no pouches or speedloaders.
+    pub fn new(meshes: CartridgeMeshes) -> Inventory {
+        Inventory {
+            pouches: Vec::new(),
+            speedloaders: Vec::new(),
+
+            meshes: meshes,
+        }
+    }
+
+    /// Counts the loose rounds of the named type across every pouch.
+    pub fn count(&self, name: &str) -> usize {
+        self.pouches.iter().map(|pouch| pouch.count(name)).sum()
+    }
+
+    /// Puts a round of the specified type in the first pouch with room for it.
+    pub fn put(&mut self, spec: Arc<CartridgeSpec>) -> Result<(), Arc<CartridgeSpec>> {
+        let mut spec = spec;
+        for pouch in &mut self.pouches {
+            spec = match pouch.put(spec) {
+                Ok(()) => return Ok(()),
+                Err(spec) => spec,
+            };
+        }
+
+        Err(spec)
+    }
+
+    /// Stores a cartridge that's come out of the gun as a loose round.
+    ///
+    /// Gives the cartridge back if it's spent or every pouch is full.
+    pub fn store(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if cartridge.has_fired() {
+            return Err(cartridge);
+        }
+
+        match self.put(cartridge.spec.clone()) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(cartridge),
+        }
+    }
+
+    /// Takes a loose round of the named type out of the first pouch that has one.
+    pub fn take_cartridge(&mut self, name: &str) -> Option<Cartridge> {
+        let spec = self.pouches.iter_mut().filter_map(|pouch| pouch.take(name)).next()?;
+        Some(Cartridge::new(&self.meshes, spec))
+    }
+
+    /// Gets the index of the fullest speedloader holding only cartridges that `accepts` allows,
+    /// or `None` if there aren't any.
+    pub fn ready_speedloader<F>(&self, accepts: F) -> Option<usize>
+        where F: Fn(&CartridgeSpec) -> bool
+    {
+        self.speedloaders
+            .iter()
+            .enumerate()
+            .filter(|&(_, speedloader)| !speedloader.is_empty())
+            .filter(|&(_, speedloader)| speedloader.rounds.iter().all(|spec| accepts(spec)))
+            .max_by_key(|&(_, speedloader)| speedloader.len())
+            .map(|(index, _)| index)
+    }
+
+    /// Takes every cartridge out of the speedloader at `index` so that they can be loaded.
+    pub fn take_speedloader(&mut self, index: usize) -> Vec<Cartridge> {
+        let meshes = &self.meshes;
+        self.speedloaders[index].rounds
+            .drain(..)
+            .map(|spec| Cartridge::new(meshes, spec))
+            .collect()
+    }
+
+    /// Puts cartridges the gun didn't take back in the speedloader at `index`.
+    ///
+    /// Returns any that are spent or don't fit.
+    pub fn return_to_speedloader(&mut self, index: usize, cartridges: Vec<Cartridge>) -> Vec<Cartridge> {
+        let speedloader = &mut self.speedloaders[index];
+        let mut remaining = Vec::new();
+        for cartridge in cartridges {
+            if cartridge.has_fired() || speedloader.is_full() {
+                remaining.push(cartridge);
+            } else {
+                speedloader.rounds.push(cartridge.spec.clone());
+            }
+        }
+
+        remaining
+    }
+
+    /// Fills every speedloader with loose rounds of the named type.
+    ///
+    /// Speedloaders that already hold a different type are left alone, since mixing loads makes
+    /// it impossible to know what's going to fire.
+    pub fn fill_speedloaders(&mut self, name: &str) {
+        for speedloader in &mut self.speedloaders {
+            if speedloader.rounds.iter().any(|spec| spec.name != name) {
+                continue;
+            }
+
+            while !speedloader.is_full() {
+                match self.pouches.iter_mut().filter_map(|pouch| pouch.take(name)).next() {
+                    Some(spec) => speedloader.rounds.push(spec),
+                    None => return,
+                }
+            }
+        }
+    }
+
+    /// Takes as many rounds from `pickup` as there's room for.
+    ///
+    /// Returns the number of rounds picked up.
+    pub fn pick_up(&mut self, pickup: &mut AmmoPickup) -> usize {
+        let mut picked_up = 0;
+        while let Some(spec) = pickup.rounds.pop() {
+            if let Err(spec) = self.put(spec) {
+                pickup.rounds.push(spec);
+                break;
+            }
+
+            picked_up += 1;
+        }
+
+        picked_up
+    }
+}
+
+/// A pile of loose rounds lying in the world, waiting to be picked up.
+#[derive(Debug)]
+pub struct AmmoPickup {
+    pub transform: Transform,
+    pub mesh_renderer: MeshRenderer,
+
+    rounds: Vec<Arc<CartridgeSpec>>,
+}
+
+impl AmmoPickup {
+    pub fn new(mesh: &Mesh, position: Point, rounds: Vec<Arc<CartridgeSpec>>) -> AmmoPickup {
+        let mut transform = Transform::new();
+        transform.set_position(position);
+        let mesh_renderer = MeshRenderer::new(&mesh, &transform);
+
+        AmmoPickup {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+
+            rounds: rounds,
+        }
+    }
+
+    /// Creates a pickup holding `count` rounds of the specified type.
+    pub fn with_count(mesh: &Mesh, position: Point, spec: Arc<CartridgeSpec>, count: usize) -> AmmoPickup {
+        AmmoPickup::new(mesh, position, vec![spec; count])
+    }
+
+    pub fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty()
+    }
+}
@@ -0,0 +1,487 @@
+use bore::{Bore, Firing};
+use bullet::BulletPool;
+use cartridge::CartridgeSpec;
+use collision::Shape;
+use gun::{Cartridge, HammerState};
+use gunship::*;
+use gunship::math::*;
+use gunship::mesh_renderer::MeshRenderer;
+use gunship::resource::Mesh;
+use gunship::transform::Transform;
+use magazine::Magazine;
+use physics::{InertiaTensor, Integrator, Rigidbody};
+use recoil::Recoil;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use weapon::{ActionEvent, Weapon};
+
+/// The angle (in radians) the lever swings down to when fully open.
+const LEVER_OPEN_ANGLE: f32 = 1.0;
+
+/// How long (in seconds) it takes to swing the lever all the way open or closed.
+const LEVER_TIME: f32 = 0.2;
+
+/// The spacing (in meters) between cartridges lined up in the tube magazine.
+const CARTRIDGE_SPACING: f32 = 0.06;
+
+/// The speed (in meters per second) at which the extractor throws cartridges out of the top of
+/// the receiver.
+const EJECT_SPEED: f32 = 2.5;
+
+/// A lever-action rifle fed from a tube magazine under the barrel.
+///
+/// Every step of the action is done by hand. Swinging the lever open extracts and ejects
+/// whatever's in the chamber, cocks the hammer, and lifts the next round out of the tube on the
+/// carrier. Closing the lever pushes that round into the chamber. Cartridges are loaded into the
+/// tube one at a time through the loading gate while the lever is closed.
+#[derive(Debug)]
+pub struct LeverRifle {
+    pub transform: Transform,
+    pub mesh_renderer: MeshRenderer,
+    pub rigidbody: Rigidbody,
+    pub recoil: Recoil,
+
+    /// The chamberings of the cartridges the gun can be loaded with.
+    pub chamberings: Vec<String>,
+
+    pub lever_transform: Transform,
+    pub lever_renderer: MeshRenderer,
+
+    /// The tube magazine. Cartridges pushed in through the loading gate shove the rest of the
+    /// column forward, so the last one loaded is the first one fed.
+    tube: Magazine,
+    carrier: Option<Cartridge>,
+    chamber: Option<Cartridge>,
+    hammer: HammerState,
+
+    /// The current angle of the lever, where 0 is closed against the stock.
+    lever_angle: f32,
+
+    /// Whether the lever has been swung all the way open since it was last closed.
+    lever_open: bool,
+
+    /// Whether the lever is being held, or is swinging back closed after being let go.
+    lever_held: bool,
+    lever_returning: bool,
+
+    lever_offset: Vector3,
+    lever_pivot: Vector3,
+    chamber_offset: Vector3,
+    carrier_offset: Vector3,
+    tube_offset: Vector3,
+
+    pub bore: Bore,
+
+    events: Vec<ActionEvent>,
+    ejected: Vec<Cartridge>,
+
+    bullets: Arc<Mutex<BulletPool>>,
+}
+
+impl LeverRifle {
+    pub fn new(
+        mesh: &Mesh,
+        lever_mesh: &Mesh,
+        bullets: Arc<Mutex<BulletPool>>,
+        tube_capacity: usize,
+        start_pos: Point,
+        start_orientation: Orientation,
+    ) -> LeverRifle {
+        let mut transform = Transform::new();
+        transform.set_position(start_pos);
+        transform.set_orientation(start_orientation);
+        let mesh_renderer = MeshRenderer::new(&mesh, &transform);
+        let mut rigidbody = Rigidbody::new();
+        rigidbody.integrator = Integrator::VelocityVerlet;
+
+        // Approximate the rifle as a long solid box.
+        rigidbody.mass = 3.0;
+        rigidbody.inertia = InertiaTensor::from_shape(
+            &Shape::Obb { half_extents: Vector3::new(0.02, 0.05, 0.45) },
+            rigidbody.mass,
+        );
+        rigidbody.teleport(start_pos, start_orientation);
+
+        let mut lever_transform = Transform::new();
+        lever_transform.set_position(start_pos);
+        lever_transform.set_scale(Vector3::new(0.01, 0.01, 0.12));
+        let lever_renderer = MeshRenderer::new(&lever_mesh, &lever_transform);
+
+        LeverRifle {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+            rigidbody: rigidbody,
+            recoil: Recoil {
+                bore_offset: Vector3::new(0.0, 0.04, -0.45),
+                .. Recoil::default()
+            },
+
+            chamberings: vec!["30_30_winchester".into()],
+
+            lever_transform: lever_transform,
+            lever_renderer: lever_renderer,
+
+            tube: Magazine::new(tube_capacity),
+            carrier: None,
+            chamber: None,
+            hammer: HammerState::Down,
+
+            lever_angle: 0.0,
+            lever_open: false,
+            lever_held: false,
+            lever_returning: false,
+
+            lever_offset: Vector3::new(0.0, -0.03, 0.06),
+            lever_pivot: Vector3::new(0.0, 0.0, -0.06),
+            chamber_offset: Vector3::new(0.0, 0.04, -0.05),
+            carrier_offset: Vector3::new(0.0, 0.02, 0.0),
+            tube_offset: Vector3::new(0.0, 0.01, -0.1),
+
+            bore: Bore::new(Vector3::new(0.0, 0.04, -0.9)),
+
+            events: Vec::new(),
+            ejected: Vec::new(),
+
+            bullets: bullets,
+        }
+    }
+
+    /// Gets the current position of the hammer.
+    pub fn hammer(&self) -> HammerState {
+        self.hammer
+    }
+
+    pub fn tube(&self) -> &Magazine {
+        &self.tube
+    }
+
+    pub fn chamber(&self) -> &Option<Cartridge> {
+        &self.chamber
+    }
+
+    pub fn is_lever_open(&self) -> bool {
+        self.lever_open
+    }
+
+    /// Takes all action events that have happened since the last call.
+    pub fn drain_events(&mut self) -> Vec<ActionEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Takes every cartridge the lever has thrown out since the last call.
+    pub fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        mem::replace(&mut self.ejected, Vec::new())
+    }
+
+    /// Pulls the trigger, dropping the hammer if it's at full cock.
+    ///
+    /// Blocked at half-cock and while the lever is even partly open.
+    pub fn pull_trigger(&mut self) {
+        // Wait out a hang-fire before the hammer can fall again.
+        if self.lever_angle > 0.0 || self.hammer == HammerState::HalfCock || self.bore.is_hanging_fire() {
+            self.events.push(ActionEvent::TriggerBlocked);
+            return;
+        }
+
+        if self.hammer != HammerState::FullCock {
+            return;
+        }
+        self.hammer = HammerState::Down;
+
+        let event = match self.chamber.as_mut() {
+            Some(cartridge) => {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                self.bore.strike(cartridge, &mut firing)
+            },
+            None => ActionEvent::DryFired,
+        };
+        self.events.push(event);
+    }
+
+    /// Counts down a pending hang-fire, firing the cartridge once it goes off.
+    ///
+    /// If the lever has started to open the breech isn't locked, so the cartridge burns out
+    /// without sending anything down the barrel.
+    pub fn update(&mut self, delta: f32) {
+        if !self.bore.update(delta) {
+            return;
+        }
+
+        if let Some(cartridge) = self.chamber.as_mut() {
+            if self.lever_angle > 0.0 {
+                cartridge.fire();
+                return;
+            }
+
+            let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+            let event = self.bore.discharge(cartridge, &mut firing);
+            self.events.push(event);
+        }
+    }
+
+    /// Pushes a rod down the barrel, knocking out a stuck bullet.
+    ///
+    /// The barrel can only be reached with the lever open.
+    pub fn clear_bore(&mut self) {
+        if self.lever_open && self.bore.clear() {
+            self.events.push(ActionEvent::BoreCleared);
+        }
+    }
+
+    /// Works the lever, as if the player was holding it with their hand.
+    ///
+    /// Call this every frame with whether or not the player is holding the lever. While held the
+    /// lever swings open, and once it's let go it swings back closed. The action only cycles if
+    /// the lever makes it all the way open before it's closed again.
+    pub fn work_lever(&mut self, held: bool) {
+        let step = LEVER_OPEN_ANGLE / LEVER_TIME * time::delta_f32();
+
+        if held {
+            self.lever_held = true;
+            self.lever_returning = false;
+
+            if self.lever_angle < LEVER_OPEN_ANGLE {
+                self.lever_angle = (self.lever_angle + step).min(LEVER_OPEN_ANGLE);
+                if self.lever_angle >= LEVER_OPEN_ANGLE {
+                    self.open_lever();
+                }
+            }
+
+            return;
+        }
+
+        if self.lever_held {
+            self.lever_held = false;
+            self.lever_returning = true;
+        }
+
+        if self.lever_returning {
+            self.lever_angle = (self.lever_angle - step).max(0.0);
+            if self.lever_angle <= 0.0 {
+                self.lever_returning = false;
+                if self.lever_open {
+                    self.close_lever();
+                }
+            }
+        }
+    }
+
+    /// Swings the lever all the way open and leaves it there.
+    pub fn open_action(&mut self) {
+        self.lever_returning = false;
+        if !self.lever_open {
+            self.lever_angle = LEVER_OPEN_ANGLE;
+            self.open_lever();
+        }
+    }
+
+    /// Lets an open lever swing closed.
+    pub fn close_action(&mut self) {
+        if self.lever_open {
+            self.lever_returning = true;
+        }
+    }
+
+    /// Draws the hammer back to half-cock.
+    pub fn half_cock(&mut self) {
+        if self.hammer == HammerState::Down {
+            self.hammer = HammerState::HalfCock;
+            self.events.push(ActionEvent::HalfCocked);
+        }
+    }
+
+    /// Lowers the hammer from half or full cock without firing.
+    pub fn decock(&mut self) {
+        if self.hammer != HammerState::Down {
+            self.hammer = HammerState::Down;
+            self.events.push(ActionEvent::Decocked);
+        }
+    }
+
+    /// Pushes a cartridge into the tube through the loading gate.
+    ///
+    /// The carrier blocks the gate while the lever is open, and the tube only holds so many.
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if !cartridge.spec.fits(&self.chamberings) {
+            return Err(cartridge);
+        }
+
+        if self.lever_angle > 0.0 {
+            return Err(cartridge);
+        }
+
+        // TODO: Animate pushing the cartridge through the gate.
+        self.tube.push(cartridge)
+    }
+
+    /// Picks the cartridge off the carrier by hand while the lever is open.
+    pub fn pull_cartridge(&mut self) -> Option<Cartridge> {
+        if !self.lever_open {
+            return None;
+        }
+
+        self.carrier.take()
+    }
+
+    /// Gets the speed and direction cartridges are thrown when ejected, in world space.
+    pub fn eject_velocity(&self) -> Vector3 {
+        // Cartridges are thrown straight up out of the top of the receiver.
+        let direction = Vector3::new(0.1, 1.0, 0.2).normalized();
+        self.rigidbody.velocity() + self.transform.orientation() * (direction * EJECT_SPEED)
+    }
+
+    /// The lever has reached the end of its stroke: the bolt is all the way back.
+    fn open_lever(&mut self) {
+        self.lever_open = true;
+        self.events.push(ActionEvent::ActionOpened);
+
+        // The bolt rides over the hammer on its way back.
+        if self.hammer != HammerState::FullCock {
+            self.hammer = HammerState::FullCock;
+            self.events.push(ActionEvent::Cocked);
+        }
+
+        if let Some(mut cartridge) = self.chamber.take() {
+            self.bore.extract(&mut cartridge);
+            self.ejected.push(cartridge);
+            self.events.push(ActionEvent::CartridgesEjected(1));
+        }
+
+        if self.carrier.is_none() {
+            self.carrier = self.tube.pop();
+        }
+    }
+
+    /// The lever has closed again, pushing the round on the carrier into the chamber.
+    fn close_lever(&mut self) {
+        self.lever_open = false;
+        if self.chamber.is_none() {
+            self.chamber = self.carrier.take();
+        }
+        self.events.push(ActionEvent::ActionClosed);
+    }
+
+    pub fn update_transforms(&mut self) {
+        let orientation = self.transform.orientation();
+        let position = self.transform.position();
+
+        // Rotate the lever down around its pivot at the front of the trigger guard.
+        let lever_rotation = Orientation::from_eulers(-self.lever_angle, 0.0, 0.0);
+        let local_position = self.lever_offset + self.lever_pivot - lever_rotation * self.lever_pivot;
+        self.lever_transform.set_position(position + orientation * local_position);
+        self.lever_transform.set_orientation(orientation + lever_rotation);
+
+        if let Some(cartridge) = self.chamber.as_mut() {
+            cartridge.transform.set_position(position + orientation * self.chamber_offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+
+        // The carrier tips up into line with the chamber as the lever opens.
+        if let Some(cartridge) = self.carrier.as_mut() {
+            let lift = self.lever_angle / LEVER_OPEN_ANGLE;
+            let offset = self.carrier_offset + (self.chamber_offset - self.carrier_offset) * lift;
+            cartridge.transform.set_position(position + orientation * offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+
+        // The column of cartridges in the tube runs forward from the loading gate.
+        for (index, cartridge) in self.tube.iter_mut().rev().enumerate() {
+            let offset = self.tube_offset - Vector3::new(0.0, 0.0, CARTRIDGE_SPACING * index as f32);
+            cartridge.transform.set_position(position + orientation * offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+    }
+}
+
+impl Weapon for LeverRifle {
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn rigidbody(&self) -> &Rigidbody {
+        &self.rigidbody
+    }
+
+    fn rigidbody_mut(&mut self) -> &mut Rigidbody {
+        &mut self.rigidbody
+    }
+
+    fn recoil(&self) -> &Recoil {
+        &self.recoil
+    }
+
+    fn pull_trigger(&mut self) {
+        LeverRifle::pull_trigger(self);
+    }
+
+    /// Works the lever for as long as the button is held.
+    fn secondary_action(&mut self, held: bool) {
+        self.work_lever(held);
+    }
+
+    fn safety(&mut self) {
+        self.half_cock();
+    }
+
+    fn decock(&mut self) {
+        LeverRifle::decock(self);
+    }
+
+    fn is_action_open(&self) -> bool {
+        self.lever_open
+    }
+
+    fn open_action(&mut self) {
+        LeverRifle::open_action(self);
+    }
+
+    fn close_action(&mut self) {
+        LeverRifle::close_action(self);
+    }
+
+    /// There's nothing to cycle, the lever does it all.
+    fn accepts(&self, spec: &CartridgeSpec) -> bool {
+        spec.fits(&self.chamberings)
+    }
+
+    fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        LeverRifle::load_cartridge(self, cartridge)
+    }
+
+    /// A tube magazine can't be dumped out, it has to be emptied by working the lever.
+    fn unload(&mut self) -> Vec<Cartridge> {
+        Vec::new()
+    }
+
+    fn unload_one(&mut self) -> Option<Cartridge> {
+        self.pull_cartridge()
+    }
+
+    fn eject_velocity(&self) -> Vector3 {
+        LeverRifle::eject_velocity(self)
+    }
+
+    fn clear_bore(&mut self) {
+        LeverRifle::clear_bore(self);
+    }
+
+    fn update(&mut self, delta: f32) {
+        LeverRifle::update(self, delta);
+    }
+
+    fn drain_events(&mut self) -> Vec<ActionEvent> {
+        LeverRifle::drain_events(self)
+    }
+
+    fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        LeverRifle::drain_ejected(self)
+    }
+
+    fn interpolate(&mut self, alpha: f32) {
+        self.rigidbody.interpolate(&mut self.transform, alpha);
+    }
+
+    fn update_transforms(&mut self) {
+        LeverRifle::update_transforms(self);
+    }
+}
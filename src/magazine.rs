@@ -0,0 +1,63 @@
+use gun::Cartridge;
+use std::slice;
+
+/// A magazine, either a detachable box or a fixed tube.
+///
+/// Cartridges are stacked on top of each other, so the last one loaded is the first one fed into
+/// the chamber.
+#[derive(Debug)]
+pub struct Magazine {
+    cartridges: Vec<Cartridge>,
+    capacity: usize,
+}
+
+impl Magazine {
+    /// Creates a new, empty magazine that holds up to `capacity` cartridges.
+    pub fn new(capacity: usize) -> Magazine {
+        Magazine {
+            cartridges: Vec::with_capacity(capacity),
+            capacity: capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cartridges.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cartridges.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cartridges.len() >= self.capacity
+    }
+
+    /// Presses a cartridge into the top of the magazine, or gives it back if the magazine is full.
+    pub fn push(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if self.is_full() {
+            return Err(cartridge);
+        }
+
+        self.cartridges.push(cartridge);
+        Ok(())
+    }
+
+    /// Takes the cartridge off the top of the magazine.
+    pub fn pop(&mut self) -> Option<Cartridge> {
+        self.cartridges.pop()
+    }
+
+    /// Iterates over the cartridges in the magazine, bottom first.
+    pub fn iter_mut(&mut self) -> slice::IterMut<Cartridge> {
+        self.cartridges.iter_mut()
+    }
+
+    /// Removes every cartridge from the magazine, top first.
+    pub fn take_all(&mut self) -> Vec<Cartridge> {
+        self.cartridges.drain(..).rev().collect()
+    }
+}
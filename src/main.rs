@@ -1,16 +1,26 @@
 //! # Gunship First Person Shooter
-//!
-//! # TODO
-//!
-//! - Animate hammer pulling back.
-//! - Add a way to empty cartridges.
 
 extern crate gunship;
 
+pub mod ballistics;
+pub mod bolt_rifle;
+pub mod bore;
+pub mod bullet;
+pub mod cartridge;
+pub mod collision;
+pub mod config;
 pub mod gun;
+pub mod inventory;
+pub mod lever_rifle;
+pub mod magazine;
 pub mod physics;
+pub mod pistol;
 pub mod player;
+pub mod random;
+pub mod recoil;
+pub mod shotgun;
 pub mod tween;
+pub mod weapon;
 
 use gunship::*;
 use gunship::camera::Camera;
@@ -20,11 +30,22 @@ use gunship::transform::Transform;
 use gunship::mesh_renderer::MeshRenderer;
 use gunship::math::*;
 use std::mem;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use self::ballistics::Atmosphere;
+use self::bolt_rifle::BoltRifle;
+use self::bullet::BulletPool;
+use self::cartridge::CartridgeSpec;
+use self::collision::*;
+use self::config::Config;
 use self::physics::*;
+use self::pistol::Pistol;
 use self::player::*;
+use self::recoil::*;
+use self::shotgun::Shotgun;
 use self::gun::*;
+use self::inventory::{AmmoPickup, Inventory, Pouch, Speedloader};
+use self::lever_rifle::LeverRifle;
 
 pub fn main() {
     let mut builder = EngineBuilder::new();
@@ -40,12 +61,25 @@ fn setup_scene() {
     // Load all meshes for the game.
     let gun_mesh_task = resource::load_mesh("meshes/gun_small.dae");
     let cube_mesh_task = resource::load_mesh("meshes/cube.dae");
+    let cartridge_mesh_task = resource::load_mesh("meshes/bullet_small.dae");
 
     let gun_mesh = gun_mesh_task.await().expect("Failed to load gun_small.dae");
     let cube_mesh = cube_mesh_task.await().expect("Failed to load cube.dae");
+    let cartridge_mesh = cartridge_mesh_task.await().expect("Failed to load bullet_small.dae");
 
     let cube_mesh = Arc::new(cube_mesh);
 
+    // TODO: Use a proper spent casing mesh instead of a squashed cube.
+    let cartridge_meshes = CartridgeMeshes {
+        live: Arc::new(cartridge_mesh),
+        spent: cube_mesh.clone(),
+    };
+
+    let mut collision_world = CollisionWorld::new();
+
+    // Add an invisible floor so that dropped casings have something to land on.
+    collision_world.add(Collider::new(Shape::Plane, Point::new(0.0, -1.5, 0.0)));
+
     // Create static gun and bullet meshes, used for points of reference when running around.
     // TODO: Create some kind of level with a floor and some walls and stuff, some kind of actual
     // testing grounds.
@@ -54,6 +88,8 @@ fn setup_scene() {
         let mesh_renderer = MeshRenderer::new(&gun_mesh, &transform);
         transform.set_position(Point::new(0.0, 0.0, -1.0));
 
+        collision_world.add(Collider::new(Shape::Sphere { radius: 0.1 }, transform.position()));
+
         // Make the mesh "static" by ensuring the destructor won't be run.
         // TODO: Figure out a better way to keep track of static scene elements.
         mem::forget(transform);
@@ -65,6 +101,11 @@ fn setup_scene() {
         let mesh_renderer = MeshRenderer::new(&cube_mesh, &transform);
         transform.set_position(Point::new(-1.0, 0.0, 0.0));
 
+        collision_world.add(Collider::new(
+            Shape::Aabb { half_extents: Vector3::new(0.5, 0.5, 0.5) },
+            transform.position(),
+        ));
+
         // Make the mesh "static" by ensuring the destructor won't be run.
         // TODO: Figure out a better way to keep track of static scene elements.
         mem::forget(transform);
@@ -83,47 +124,178 @@ fn setup_scene() {
         mem::forget(light);
     }
 
+    // Load the cartridge definitions, falling back to a single default type if they're missing.
+    let mut cartridge_specs = Config::load("config/cartridges.ini")
+        .map(|config| CartridgeSpec::load_all(&config))
+        .unwrap_or(Vec::new());
+    if cartridge_specs.is_empty() {
+        cartridge_specs.push(Arc::new(CartridgeSpec::default()));
+    }
+
     // Create camera.
     let mut root_transform = Transform::new();
     root_transform.set_position(Point::new(0.0, 0.0, 10.0));
     let camera = Camera::new(&root_transform);
 
+    let player_shape = Shape::Capsule { radius: 0.3, half_height: 0.5 };
+    let player_collider = collision_world.add(Collider {
+        restitution: 0.0,
+        .. Collider::dynamic(player_shape, root_transform.position())
+    });
+
+    let collision_world = Arc::new(Mutex::new(collision_world));
+
+    // Wind is optional, if the file is missing bullets fly through still air.
+    let mut atmosphere = Atmosphere::default();
+    if let Ok(config) = Config::load("config/atmosphere.ini") {
+        atmosphere.apply_config(&config);
+    }
+    let atmosphere = Arc::new(Mutex::new(atmosphere));
+
+    // Every bullet fired goes into a single pool that's updated once per frame.
+    let bullets = Arc::new(Mutex::new(BulletPool::new(
+        cube_mesh.clone(),
+        collision_world.clone(),
+        atmosphere,
+    )));
+
     // Create the player avatar.
     let mut root_rigidbody = Rigidbody::new();
     root_rigidbody.mass = 70.0;
+    root_rigidbody.inertia = InertiaTensor::from_shape(&player_shape, root_rigidbody.mass);
     root_rigidbody.linear_drag = 500.0;
+    root_rigidbody.teleport(root_transform.position(), root_transform.orientation());
 
-    let gun_physics = GunPhysics {
-        linear_spring: 500.0,
-        angular_spring: 400.0,
-
+    let mut gun_physics = GunPhysics {
         position_offset: Vector3::new(0.0, -0.1, -0.3),
 
         .. GunPhysics::default()
     };
-    let gun = Revolver::new(
+
+    // Gun physics tuning is optional, if the file is missing we stick with the defaults.
+    if let Ok(config) = Config::load("config/gun_physics.ini") {
+        gun_physics.apply_config(&config);
+    }
+
+    // Seeding is optional, if there's no seed every gun seeds itself from the clock.
+    let seed: Option<u32> = Config::load("config/random.ini")
+        .ok()
+        .and_then(|config| config.get("seed"));
+
+    let mut gun = Revolver::new(
         &gun_mesh,
         &*cube_mesh,
-        cube_mesh.clone(),
+        bullets.clone(),
+        root_transform.position() + gun_physics.position_offset,
+        root_transform.orientation(),
+    );
+
+    let mut shotgun = Shotgun::new(
+        &gun_mesh,
+        &*cube_mesh,
+        bullets.clone(),
+        root_transform.position() + gun_physics.position_offset,
+        root_transform.orientation(),
+    );
+
+    if let Ok(config) = Config::load("config/shotgun.ini") {
+        shotgun.apply_config(&config);
+    }
+
+    let mut pistol = Pistol::new(
+        &gun_mesh,
+        &*cube_mesh,
+        bullets.clone(),
+        15,
+        root_transform.position() + gun_physics.position_offset,
+        root_transform.orientation(),
+    );
+
+    let mut lever_rifle = LeverRifle::new(
+        &gun_mesh,
+        &*cube_mesh,
+        bullets.clone(),
+        7,
+        root_transform.position() + gun_physics.position_offset,
+        root_transform.orientation(),
+    );
+
+    let mut bolt_rifle = BoltRifle::new(
+        &gun_mesh,
+        &*cube_mesh,
+        bullets.clone(),
+        5,
         root_transform.position() + gun_physics.position_offset,
         root_transform.orientation(),
     );
 
+    // Give each gun its own seed so that they don't all roll the same outcomes. The shotgun
+    // takes two, one for each barrel.
+    if let Some(seed) = seed {
+        gun.bore.seed(seed);
+        shotgun.seed(seed.wrapping_add(1));
+        pistol.bore.seed(seed.wrapping_add(3));
+        lever_rifle.bore.seed(seed.wrapping_add(4));
+        bolt_rifle.bore.seed(seed.wrapping_add(5));
+    }
+
+    // Start the player off with a few rounds of every type, and a couple of empty speedloaders
+    // for the revolver and stripper clips for the bolt-action.
+    let mut inventory = Inventory::new(cartridge_meshes.clone());
+    for _ in 0..4 {
+        inventory.pouches.push(Pouch::new(30));
+    }
+    for &capacity in &[6, 6, 5, 5] {
+        inventory.speedloaders.push(Speedloader::new(capacity));
+    }
+    for spec in &cartridge_specs {
+        for _ in 0..12 {
+            let _ = inventory.put(spec.clone());
+        }
+    }
+
+    // Leave a box of each type of ammo lying around to restock from.
+    let pickups = cartridge_specs
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| {
+            let position = Point::new(2.0 + index as f32, -0.8, 5.0);
+            let mut pickup = AmmoPickup::with_count(&cube_mesh, position, spec.clone(), 30);
+            pickup.transform.set_scale(Vector3::new(0.2, 0.1, 0.15));
+            pickup
+        })
+        .collect();
+
     let mut player = Player {
         camera: camera,
         transform: root_transform,
         rigidbody: root_rigidbody,
+        collider: player_collider,
+        timestep: FixedTimestep::new(120.0),
 
-        gun: gun,
+        gun: Box::new(gun),
+        holstered: vec![Box::new(shotgun), Box::new(pistol), Box::new(lever_rifle), Box::new(bolt_rifle)],
+        hip_offset: gun_physics.position_offset,
         gun_physics: gun_physics,
 
         pitch: 0.0,
         yaw: 0.0,
+        camera_kick: CameraKick::new(8.0),
+
+        inventory: inventory,
+        pickups: pickups,
+        pickup_mesh: cartridge_meshes.live.clone(),
+        casings: Vec::new(),
 
-        cartridge_mesh: cube_mesh.clone(),
+        cartridge_specs: cartridge_specs,
+        cartridge_spec: 0,
+        collision_world: collision_world,
     };
 
     engine::run_each_frame(move || {
         player.update();
+
+        // TODO: Apply damage for the hits in `BulletPool::hits()` once there are targets to damage.
+        bullets.lock().unwrap().update();
     });
 }
@@ -1,27 +1,42 @@
-use gunship::*;
+use collision::{Contact, Shape};
+use config::Config;
 use gunship::math::*;
 use gunship::transform::Transform;
 
+/// Acceleration due to gravity in meters per second squared.
+pub const GRAVITY: f32 = 9.81;
+
 #[derive(Debug)]
 pub struct Rigidbody {
     /// Mass (in kilograms) of the rigidbody.
     pub mass: f32,
 
-    /// Rotoational inertia (or moment of inertia) of the rigid body in whatever units are normally used ?_?
-    pub rotational_inertia: f32,
+    /// The inertia tensor of the rigidbody in local space, in kilogram meters squared.
+    ///
+    /// Use `InertiaTensor::from_shape()` to calculate this from the rigidbody's collider.
+    pub inertia: InertiaTensor,
 
     pub linear_drag: f32,
+
+    /// The rate at which angular momentum decays, as a fraction per second.
+    ///
+    /// Angular drag is proportional to angular momentum rather than angular velocity so that the
+    /// same value gives the same amount of damping regardless of the rigidbody's inertia.
     pub angular_drag: f32,
 
+    /// The numerical method used to advance the rigidbody each step.
+    pub integrator: Integrator,
+
     /// The current velocity of the simulation in meters per second.
     velocity: Vector3,
 
-    /// The current angular velocity represented as euler angles.
+    /// The current angular momentum of the rigidbody in world space.
     ///
-    /// The angular velocity is represented using euler angles because quaternions can't
-    /// represent rotations greater than 180 degrees. Euler angles allow for high rotation
-    /// speeds to be represented in a
-    angular_velocity: Vector3,
+    /// Angular momentum is tracked instead of angular velocity because it's conserved in the
+    /// absence of torque, whereas angular velocity changes as the rigidbody's orientation (and
+    /// therefore its world space inertia) changes. This is what lets an asymmetric body like the
+    /// revolver tumble correctly.
+    angular_momentum: Vector3,
 
     /// The total force applied to the rigidbody for the frame.
     ///
@@ -34,25 +49,92 @@ pub struct Rigidbody {
     /// Every frame all torques applied to the rigidbody are summed and used to calculate
     /// the rigidbody's angular acceleration for the frame.
     torque: Vector3,
+
+    /// The total force applied by springs for the step, measured at the rigidbody's position at
+    /// the start of the step.
+    spring_force: Vector3,
+
+    /// The combined stiffness of all springs applied for the step.
+    ///
+    /// Springs are accumulated separately from other forces because their force depends on
+    /// position, which lets higher order integrators re-evaluate them partway through a step.
+    spring_stiffness: f32,
+
+    /// The position of the rigidbody as of the last physics step.
+    ///
+    /// The rigidbody's pose is tracked separately from its transform so that the transform can be
+    /// interpolated between the previous and current steps when rendering.
+    position: Point,
+    orientation: Orientation,
+
+    /// The pose of the rigidbody as of the step before the last one.
+    previous_position: Point,
+    previous_orientation: Orientation,
 }
 
 impl Rigidbody {
     pub fn new() -> Rigidbody {
         Rigidbody {
             mass: 1.0,
-            rotational_inertia: 1.0,
+            inertia: InertiaTensor::diagonal(1.0, 1.0, 1.0),
 
             linear_drag: 0.0,
             angular_drag: 0.0,
 
+            integrator: Integrator::SymplecticEuler,
+
             velocity: Vector3::zero(),
-            angular_velocity: Vector3::zero(),
+            angular_momentum: Vector3::zero(),
 
             force: Vector3::zero(),
             torque: Vector3::zero(),
+
+            spring_force: Vector3::zero(),
+            spring_stiffness: 0.0,
+
+            position: Point::default(),
+            orientation: Orientation::default(),
+
+            previous_position: Point::default(),
+            previous_orientation: Orientation::default(),
         }
     }
 
+    /// Gets the position of the rigidbody as of the last physics step.
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    /// Gets the orientation of the rigidbody as of the last physics step.
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Sets the position of the rigidbody, overriding the current value.
+    ///
+    /// Unlike `teleport()` this doesn't reset the previous position, so the change is still
+    /// interpolated.
+    pub fn set_position(&mut self, position: Point) {
+        self.position = position;
+    }
+
+    /// Sets the orientation of the rigidbody, overriding the current value.
+    ///
+    /// Unlike `teleport()` this doesn't reset the previous orientation, so the change is still
+    /// interpolated.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Moves the rigidbody directly to the specified pose without interpolating.
+    pub fn teleport(&mut self, position: Point, orientation: Orientation) {
+        self.position = position;
+        self.orientation = orientation;
+
+        self.previous_position = position;
+        self.previous_orientation = orientation;
+    }
+
     /// Retrieves the current velocity of the rigidbody.
     pub fn velocity(&self) -> Vector3 {
         self.velocity
@@ -68,9 +150,25 @@ impl Rigidbody {
         self.velocity = self.velocity + velocity;
     }
 
+    /// Retrieves the current angular velocity of the rigidbody in world space.
+    ///
+    /// The angular velocity is the axis of rotation scaled by the rate of rotation in radians per
+    /// second.
+    pub fn angular_velocity(&self) -> Vector3 {
+        self.inertia.inverse().transform_world(self.orientation, self.angular_momentum)
+    }
+
+    /// Retrieves the current angular momentum of the rigidbody in world space.
+    pub fn angular_momentum(&self) -> Vector3 {
+        self.angular_momentum
+    }
+
     /// Adds the specified value to the current angular velocity of the rigidbody.
+    ///
+    /// `angular_velocity` is in world space.
     pub fn add_angular_velocity(&mut self, angular_velocity: Vector3) {
-        self.angular_velocity = self.angular_velocity + angular_velocity;
+        let momentum = self.inertia.transform_world(self.orientation, angular_velocity);
+        self.angular_momentum = self.angular_momentum + momentum;
     }
 
     /// Applies the specified force to the rigidbody.
@@ -78,74 +176,802 @@ impl Rigidbody {
         self.force = self.force + force;
     }
 
+    /// Applies the specified force at a point on the rigidbody.
+    ///
+    /// Both `force` and `point` are in world space. Any force not applied through the center of
+    /// mass also produces a torque.
+    pub fn apply_force_at_point(&mut self, force: Vector3, point: Point) {
+        let lever = point - self.position;
+        self.force = self.force + force;
+        self.torque += lever.cross(force);
+    }
+
+    /// Applies the specified force at a point on the rigidbody.
+    ///
+    /// Both `force` and `point` are in the rigidbody's local space.
+    pub fn apply_local_force_at_point(&mut self, force: Vector3, point: Vector3) {
+        let (force, point) = self.local_to_world(force, point);
+        self.apply_force_at_point(force, point);
+    }
+
+    /// Applies an instantaneous impulse (in newton seconds) through the rigidbody's center of mass.
+    pub fn apply_impulse(&mut self, impulse: Vector3) {
+        self.velocity = self.velocity + impulse / self.mass;
+    }
+
+    /// Applies an instantaneous impulse (in newton seconds) at a point on the rigidbody.
+    ///
+    /// Both `impulse` and `point` are in world space. Unlike forces, impulses take effect
+    /// immediately rather than being accumulated until the next step.
+    pub fn apply_impulse_at_point(&mut self, impulse: Vector3, point: Point) {
+        let lever = point - self.position;
+        self.velocity = self.velocity + impulse / self.mass;
+        self.angular_momentum = self.angular_momentum + lever.cross(impulse);
+    }
+
+    /// Applies an instantaneous impulse (in newton seconds) at a point on the rigidbody.
+    ///
+    /// Both `impulse` and `point` are in the rigidbody's local space.
+    pub fn apply_local_impulse_at_point(&mut self, impulse: Vector3, point: Vector3) {
+        let (impulse, point) = self.local_to_world(impulse, point);
+        self.apply_impulse_at_point(impulse, point);
+    }
+
     /// Applies the specified torque to the rigidbody.
     pub fn apply_torque(&mut self, torque: Vector3) {
         self.torque += torque;
     }
 
-    pub fn update(&mut self, transform: &mut Transform) {
-        // Calculate the acceleration from the forces, then use the acceleration to
-        // update the velocity.
-        let damping = -self.linear_drag * self.velocity;
-        let force = self.force + damping;
+    /// Applies whatever torque is needed to give the rigidbody the specified angular
+    /// acceleration, accounting for its current inertia.
+    pub fn apply_angular_acceleration(&mut self, angular_acceleration: Vector3) {
+        self.torque += self.inertia.transform_world(self.orientation, angular_acceleration);
+    }
 
-        let acceleration = force / self.mass;
-        self.velocity = self.velocity + acceleration * time::delta_f32();
+    /// Applies a spring force pulling the rigidbody towards `anchor` with the specified
+    /// stiffness (in newtons per meter).
+    ///
+    /// Prefer this over computing the spring force and passing it to `apply_force()`, since it
+    /// allows the integrator to account for the spring changing over the course of the step.
+    pub fn apply_spring(&mut self, anchor: Point, stiffness: f32) {
+        self.spring_force = self.spring_force + (anchor - self.position) * stiffness;
+        self.spring_stiffness += stiffness;
+    }
 
-        transform.translate(self.velocity * time::delta_f32());
+    /// Converts a local space vector and point into world space based on the rigidbody's current
+    /// pose.
+    fn local_to_world(&self, vector: Vector3, point: Vector3) -> (Vector3, Point) {
+        (self.orientation * vector, self.position + self.orientation * point)
+    }
 
-        // Calculate angular acceleration from the torques, then use angular acceleration
-        // to update the angular velocity.
-        let damping_torque = -self.angular_drag * self.angular_velocity;
-        let torque = self.torque + damping_torque;
+    /// Advances the simulation by `delta` seconds.
+    ///
+    /// This should be called from a fixed timestep (see `FixedTimestep`) rather than once per
+    /// frame, otherwise stiff forces like the gun spring become unstable on frame hitches.
+    pub fn step(&mut self, delta: f32) {
+        self.previous_position = self.position;
+        self.previous_orientation = self.orientation;
 
-        let angular_acceleration = torque / self.rotational_inertia;
-        self.angular_velocity = self.angular_velocity + angular_acceleration * time::delta_f32();
+        // Calculate the acceleration from the forces, then use the acceleration to
+        // update the velocity. Springs and drag are re-evaluated as the integrator samples
+        // different displacements and velocities over the course of the step.
+        let force = self.force + self.spring_force;
+        let (stiffness, drag, mass) = (self.spring_stiffness, self.linear_drag, self.mass);
+        let (displacement, velocity) = self.integrator.integrate(self.velocity, delta, |displacement, velocity| {
+            (force - stiffness * displacement - drag * velocity) / mass
+        });
+
+        self.position = self.position + displacement;
+        self.velocity = velocity;
 
-        let Vector3 { x, y, z } = self.angular_velocity * time::delta_f32();
-        transform.rotate(Orientation::from_eulers(x, y, z));
+        // Integrate the torques to update the angular momentum. The world space inertia depends on
+        // orientation, which we treat as constant over the step.
+        let torque = self.torque;
+        let drag = self.angular_drag;
+        let (angular_impulse, angular_momentum) = self.integrator.integrate(self.angular_momentum, delta, |_, momentum| {
+            torque - drag * momentum
+        });
+
+        self.angular_momentum = angular_momentum;
+
+        // Convert the momentum accumulated over the step into a rotation and apply it to the
+        // orientation as a quaternion, which avoids the gimbal problems of integrating euler
+        // angles.
+        let rotation = self.inertia.inverse().transform_world(self.orientation, angular_impulse);
+        self.orientation = rotate_orientation(self.orientation, rotation);
 
         // Force and torque are both instantaneous. They're accumlated by all forces acting on the
-        // rigidbody during the frame, applied all at once, and then the total is reset for the
-        // next frame.
+        // rigidbody during the step, applied all at once, and then the total is reset for the
+        // next step.
         self.force = Vector3::zero();
         self.torque = Vector3::zero();
+        self.spring_force = Vector3::zero();
+        self.spring_stiffness = 0.0;
+    }
+
+    /// Updates `transform` to a blend of the previous and current physics steps.
+    ///
+    /// `alpha` is the fraction of a step that has elapsed since the last step, as returned by
+    /// `FixedTimestep::alpha()`.
+    pub fn interpolate(&self, transform: &mut Transform, alpha: f32) {
+        let offset = self.position - self.previous_position;
+        transform.set_position(self.previous_position + offset * alpha);
+        transform.set_orientation(lerp_orientation(self.previous_orientation, self.orientation, alpha));
+    }
+
+    /// Resolves a contact between this rigidbody and a static collider.
+    ///
+    /// `contact.a` must be the collider attached to this rigidbody, which is the case for contacts
+    /// retrieved with `CollisionWorld::contacts_with()`. The rigidbody is pushed out of the other
+    /// collider, then its velocity along the contact normal is reflected according to the contact's
+    /// restitution and its tangential velocity is reduced by friction.
+    pub fn resolve_contact(&mut self, contact: &Contact) {
+        self.position = self.position + contact.normal * contact.depth;
+
+        // Only resolve velocity if the rigidbody is moving into the other collider, otherwise
+        // they're already separating.
+        let normal_speed = self.velocity.dot(contact.normal);
+        if normal_speed >= 0.0 {
+            return;
+        }
+
+        let normal_change = -(1.0 + contact.restitution) * normal_speed;
+        self.velocity = self.velocity + contact.normal * normal_change;
+
+        // Coulomb friction: the tangential change in velocity can't exceed the normal change
+        // scaled by the friction coefficient, and can't reverse the direction of sliding.
+        let tangent = self.velocity - contact.normal * self.velocity.dot(contact.normal);
+        let tangent_speed = tangent.magnitude();
+        if tangent_speed > ::std::f32::EPSILON {
+            let friction_change = (contact.friction * normal_change).min(tangent_speed);
+            self.velocity = self.velocity - tangent / tangent_speed * friction_change;
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+/// A damped spring pulling a body towards a target.
+#[derive(Debug, Clone, Copy)]
+pub struct Spring {
+    /// How strongly the spring pulls towards the target.
+    ///
+    /// For linear springs this is in newtons per meter. For angular springs it's the angular
+    /// acceleration per radian of offset, so that it's independent of the body's inertia.
+    pub stiffness: f32,
+
+    /// How strongly the spring resists motion, relative to critical damping.
+    ///
+    /// A value of 1 is critically damped and returns to the target as fast as possible without
+    /// overshooting. Smaller values overshoot and oscillate, larger values return more slowly.
+    pub damping_ratio: f32,
+}
+
+impl Spring {
+    /// Calculates the damping coefficient that critically damps a spring of the specified
+    /// stiffness attached to a body with the specified mass.
+    pub fn critical_damping(stiffness: f32, mass: f32) -> f32 {
+        2.0 * (stiffness * mass).sqrt()
+    }
+
+    /// Calculates the damping coefficient for the spring when attached to a body with the
+    /// specified mass.
+    pub fn damping(&self, mass: f32) -> f32 {
+        self.damping_ratio * Spring::critical_damping(self.stiffness, mass)
+    }
+}
+
+/// Keeps the player's gun floating in front of them using a pair of damped springs.
+#[derive(Debug, Clone, Copy)]
 pub struct GunPhysics {
-    pub linear_spring: f32,
-    pub angular_spring: f32,
+    pub linear_spring: Spring,
+    pub angular_spring: Spring,
+
+    /// The furthest (in meters) the gun can be pulled away from its target position.
+    pub max_position_offset: f32,
 
     pub position_offset: Vector3,
 
+    /// How the gun is turned relative to the player, e.g. tilted to show off the cylinder.
+    pub held_orientation: Orientation,
+
     pub target_position: Point,
     pub target_orientation: Orientation,
+    pub target_velocity: Vector3,
 }
 
 impl GunPhysics {
-    pub fn update_target(&mut self, target_transform: &Transform) {
-        self.target_position = target_transform.position() + target_transform.orientation() * self.position_offset;
-        self.target_orientation = target_transform.orientation();
+    /// Applies any gun physics settings found in `config`, leaving the others unchanged.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.linear_spring.stiffness = config.get_or("linear_stiffness", self.linear_spring.stiffness);
+        self.linear_spring.damping_ratio = config.get_or("linear_damping_ratio", self.linear_spring.damping_ratio);
+        self.angular_spring.stiffness = config.get_or("angular_stiffness", self.angular_spring.stiffness);
+        self.angular_spring.damping_ratio = config.get_or("angular_damping_ratio", self.angular_spring.damping_ratio);
+        self.max_position_offset = config.get_or("max_position_offset", self.max_position_offset);
+    }
+
+    pub fn update_target(&mut self, target: &Rigidbody) {
+        self.target_position = target.position() + target.orientation() * self.position_offset;
+        self.target_orientation = target.orientation() + self.held_orientation;
+        self.target_velocity = target.velocity();
+    }
+
+    pub fn update(&mut self, rigidbody: &mut Rigidbody) {
+        // Keep the gun from getting too far from the target, otherwise fast movement can leave
+        // it trailing behind.
+        let offset = rigidbody.position() - self.target_position;
+        if offset.magnitude() > self.max_position_offset {
+            let clamped = self.target_position + offset.normalized() * self.max_position_offset;
+            rigidbody.set_position(clamped);
+        }
+
+        // Pull the gun towards its target position. Damping is relative to the target's velocity
+        // so that the gun doesn't lag behind when the player is moving.
+        let damping = self.linear_spring.damping(rigidbody.mass);
+        rigidbody.linear_drag = damping;
+        rigidbody.apply_force(self.target_velocity * damping);
+        rigidbody.apply_spring(self.target_position, self.linear_spring.stiffness);
+
+        // Calculate torque. The angular spring is scaled by the gun's inertia so that the same
+        // stiffness feels the same regardless of the shape of the gun. Angular drag is already
+        // independent of inertia, so the damping is calculated as if for a unit mass.
+        //
+        // The offset is the shortest rotation between the two orientations rather than the
+        // difference in euler angles, which flips around when the player looks straight up or
+        // down.
+        rigidbody.angular_drag = self.angular_spring.damping(1.0);
+        let offset = orientation_offset(rigidbody.orientation(), self.target_orientation);
+        rigidbody.apply_angular_acceleration(-self.angular_spring.stiffness * offset);
+    }
+}
+
+impl Default for GunPhysics {
+    fn default() -> GunPhysics {
+        GunPhysics {
+            linear_spring: Spring {
+                stiffness: 500.0,
+                damping_ratio: 0.45,
+            },
+            angular_spring: Spring {
+                stiffness: 400.0,
+                damping_ratio: 0.5,
+            },
+
+            max_position_offset: 0.25,
+
+            position_offset: Vector3::zero(),
+            held_orientation: Orientation::default(),
+
+            target_position: Point::default(),
+            target_orientation: Orientation::default(),
+            target_velocity: Vector3::zero(),
+        }
+    }
+}
+
+/// The rotational inertia of a rigidbody about its center of mass, in local space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InertiaTensor(pub [[f32; 3]; 3]);
+
+impl InertiaTensor {
+    pub fn diagonal(x: f32, y: f32, z: f32) -> InertiaTensor {
+        InertiaTensor([
+            [x, 0.0, 0.0],
+            [0.0, y, 0.0],
+            [0.0, 0.0, z],
+        ])
+    }
+
+    /// Calculates the inertia tensor for a solid body of the specified shape and mass.
+    ///
+    /// Planes are infinite and so have infinite inertia, meaning they can never be rotated.
+    pub fn from_shape(shape: &Shape, mass: f32) -> InertiaTensor {
+        match *shape {
+            Shape::Sphere { radius } => {
+                let inertia = 0.4 * mass * radius * radius;
+                InertiaTensor::diagonal(inertia, inertia, inertia)
+            },
+
+            Shape::Aabb { half_extents } | Shape::Obb { half_extents } => {
+                let Vector3 { x, y, z } = half_extents;
+                InertiaTensor::diagonal(
+                    mass / 3.0 * (y * y + z * z),
+                    mass / 3.0 * (x * x + z * z),
+                    mass / 3.0 * (x * x + y * y),
+                )
+            },
+
+            Shape::Capsule { radius, half_height } => {
+                // Split the mass between the cylinder and the two hemispherical caps by volume,
+                // then sum the inertia of each part about the capsule's center.
+                let height = half_height * 2.0;
+                let radius_squared = radius * radius;
+                let cylinder_volume = PI * radius_squared * height;
+                let caps_volume = 4.0 / 3.0 * PI * radius_squared * radius;
+                let cylinder_mass = mass * cylinder_volume / (cylinder_volume + caps_volume);
+                let caps_mass = mass - cylinder_mass;
+
+                let axial = cylinder_mass * radius_squared * 0.5 + caps_mass * radius_squared * 0.4;
+                let lateral = cylinder_mass * (radius_squared / 4.0 + height * height / 12.0)
+                            + caps_mass * (radius_squared * 0.4 + height * height / 4.0 + height * radius * 3.0 / 8.0);
+
+                InertiaTensor::diagonal(lateral, axial, lateral)
+            },
+
+            Shape::Plane => InertiaTensor::diagonal(::std::f32::INFINITY, ::std::f32::INFINITY, ::std::f32::INFINITY),
+        }
+    }
+
+    /// Calculates the inverse of the tensor.
+    ///
+    /// Returns the zero tensor if the tensor isn't invertible, which causes torques to have no
+    /// effect.
+    pub fn inverse(&self) -> InertiaTensor {
+        let m = &self.0;
+
+        // Tensors calculated from shapes are diagonal, which lets us handle infinite inertia
+        // cleanly.
+        let is_diagonal = m[0][1] == 0.0 && m[0][2] == 0.0 && m[1][0] == 0.0
+                       && m[1][2] == 0.0 && m[2][0] == 0.0 && m[2][1] == 0.0;
+        if is_diagonal {
+            let invert = |value: f32| if value == 0.0 { 0.0 } else { 1.0 / value };
+            return InertiaTensor::diagonal(invert(m[0][0]), invert(m[1][1]), invert(m[2][2]));
+        }
+
+        let cofactor = |row_a: usize, row_b: usize, col_a: usize, col_b: usize| {
+            m[row_a][col_a] * m[row_b][col_b] - m[row_a][col_b] * m[row_b][col_a]
+        };
+
+        let determinant = m[0][0] * cofactor(1, 2, 1, 2)
+                        - m[0][1] * cofactor(1, 2, 0, 2)
+                        + m[0][2] * cofactor(1, 2, 0, 1);
+        if determinant.abs() <= ::std::f32::EPSILON {
+            return InertiaTensor::diagonal(0.0, 0.0, 0.0);
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        InertiaTensor([
+            [
+                cofactor(1, 2, 1, 2) * inverse_determinant,
+                -cofactor(0, 2, 1, 2) * inverse_determinant,
+                cofactor(0, 1, 1, 2) * inverse_determinant,
+            ],
+            [
+                -cofactor(1, 2, 0, 2) * inverse_determinant,
+                cofactor(0, 2, 0, 2) * inverse_determinant,
+                -cofactor(0, 1, 0, 2) * inverse_determinant,
+            ],
+            [
+                cofactor(1, 2, 0, 1) * inverse_determinant,
+                -cofactor(0, 2, 0, 1) * inverse_determinant,
+                cofactor(0, 1, 0, 1) * inverse_determinant,
+            ],
+        ])
+    }
+
+    /// Multiplies a local space vector by the tensor.
+    pub fn transform(&self, vector: Vector3) -> Vector3 {
+        let m = &self.0;
+        Vector3::new(
+            m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+            m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+            m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z,
+        )
+    }
+
+    /// Multiplies a world space vector by the tensor for a body with the specified orientation.
+    ///
+    /// This is equivalent to multiplying by the world space tensor `R * I * R^T`, but avoids
+    /// building the rotation matrix.
+    pub fn transform_world(&self, orientation: Orientation, vector: Vector3) -> Vector3 {
+        let right = orientation * Vector3::new(1.0, 0.0, 0.0);
+        let up = orientation * Vector3::new(0.0, 1.0, 0.0);
+        let back = orientation * Vector3::new(0.0, 0.0, 1.0);
+
+        let local = self.transform(Vector3::new(vector.dot(right), vector.dot(up), vector.dot(back)));
+        right * local.x + up * local.y + back * local.z
+    }
+}
+
+/// The numerical method used to advance a `Rigidbody` through a step.
+///
+/// The integrators trade cost for stability: explicit Euler gains energy and quickly blows up
+/// on stiff springs, symplectic Euler is just as cheap but keeps energy bounded, velocity Verlet
+/// is second order, and RK4 is the most accurate but samples the forces four times per step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    ExplicitEuler,
+    SymplecticEuler,
+    VelocityVerlet,
+    Rk4,
+}
+
+impl Integrator {
+    /// Advances a body moving at `velocity` by `delta` seconds.
+    ///
+    /// `acceleration` gives the acceleration of the body for a given displacement (relative to
+    /// its position at the start of the step) and velocity. Returns the body's total displacement
+    /// over the step and its final velocity.
+    pub fn integrate<F>(self, velocity: Vector3, delta: f32, acceleration: F) -> (Vector3, Vector3)
+        where F: Fn(Vector3, Vector3) -> Vector3
+    {
+        let start = Vector3::zero();
+
+        match self {
+            Integrator::ExplicitEuler => {
+                let acceleration = acceleration(start, velocity);
+                (velocity * delta, velocity + acceleration * delta)
+            },
+
+            Integrator::SymplecticEuler => {
+                let velocity = velocity + acceleration(start, velocity) * delta;
+                (velocity * delta, velocity)
+            },
+
+            Integrator::VelocityVerlet => {
+                let start_acceleration = acceleration(start, velocity);
+                let displacement = velocity * delta + start_acceleration * (0.5 * delta * delta);
+
+                // The acceleration at the end of the step depends on the final velocity, so
+                // estimate it with an Euler step.
+                let predicted_velocity = velocity + start_acceleration * delta;
+                let end_acceleration = acceleration(displacement, predicted_velocity);
+
+                (displacement, velocity + (start_acceleration + end_acceleration) * (0.5 * delta))
+            },
+
+            Integrator::Rk4 => {
+                let half = delta * 0.5;
+
+                let k1_velocity = velocity;
+                let k1_acceleration = acceleration(start, k1_velocity);
+
+                let k2_velocity = velocity + k1_acceleration * half;
+                let k2_acceleration = acceleration(k1_velocity * half, k2_velocity);
+
+                let k3_velocity = velocity + k2_acceleration * half;
+                let k3_acceleration = acceleration(k2_velocity * half, k3_velocity);
+
+                let k4_velocity = velocity + k3_acceleration * delta;
+                let k4_acceleration = acceleration(k3_velocity * delta, k4_velocity);
+
+                let displacement = (k1_velocity + k2_velocity * 2.0 + k3_velocity * 2.0 + k4_velocity) * (delta / 6.0);
+                let acceleration = (k1_acceleration + k2_acceleration * 2.0 + k3_acceleration * 2.0 + k4_acceleration) * (delta / 6.0);
+
+                (displacement, velocity + acceleration)
+            },
+        }
+    }
+}
+
+/// Accumulates frame time and doles it out in fixed size physics steps.
+///
+/// Running physics at a fixed rate keeps the simulation stable and makes it behave the same
+/// regardless of frame rate. Any time left over that doesn't make up a full step is carried over
+/// to the next frame, and `alpha()` reports how far into the next step we are so that rendering
+/// can interpolate between steps.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    /// The length of a single step in seconds.
+    pub step: f32,
+
+    /// The maximum number of steps run in a single frame.
+    ///
+    /// If a frame takes long enough that more steps would be needed to catch up, the extra time
+    /// is dropped. This keeps a long hitch from causing even longer frames as physics tries to
+    /// catch up (the "spiral of death").
+    pub max_steps: usize,
+
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Creates a new timestep that runs `rate` steps per second.
+    pub fn new(rate: f32) -> FixedTimestep {
+        FixedTimestep {
+            step: 1.0 / rate,
+            max_steps: 8,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds the frame's elapsed time to the accumulator, returning the number of steps that
+    /// should be run this frame.
+    pub fn advance(&mut self, delta: f32) -> usize {
+        self.accumulator += delta;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        if steps > self.max_steps {
+            steps = self.max_steps;
+            self.accumulator = 0.0;
+        }
+
+        steps
+    }
+
+    /// Gets the fraction of a step that has accumulated but not yet been simulated, in the range
+    /// [0, 1).
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+/// Blends between two orientations, taking the shortest path between them.
+///
+/// This normalizes the linear blend of the two quaternions rather than doing a proper slerp, which
+/// is close enough for the small differences between two physics steps.
+fn lerp_orientation(from: Orientation, to: Orientation, t: f32) -> Orientation {
+    let from = Quaternion::from(from);
+    let to = Quaternion::from(to);
+
+    // `q` and `-q` represent the same rotation, so flip `to` if needed to go the short way around.
+    let dot = from.w * to.w + from.x * to.x + from.y * to.y + from.z * to.z;
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+
+    Orientation::from(normalize_quaternion(Quaternion {
+        w: from.w + (to.w * sign - from.w) * t,
+        x: from.x + (to.x * sign - from.x) * t,
+        y: from.y + (to.y * sign - from.y) * t,
+        z: from.z + (to.z * sign - from.z) * t,
+    }))
+}
+
+/// Rotates `orientation` by the rotation vector `rotation`, in world space.
+///
+/// The rotation vector is the axis of rotation scaled by the angle of rotation in radians.
+pub fn rotate_orientation(orientation: Orientation, rotation: Vector3) -> Orientation {
+    let angle = rotation.magnitude();
+    if angle <= ::std::f32::EPSILON {
+        return orientation;
     }
 
-    pub fn update(&mut self, rigidbody: &mut Rigidbody, transform: &Transform) {
-        // Override values for debug purposes.
-        self.linear_spring = 500.0;
-        self.angular_spring = 400.0;
+    let axis = rotation / angle;
+    let (sin, cos) = (angle * 0.5).sin_cos();
+    let delta = Quaternion {
+        w: cos,
+        x: axis.x * sin,
+        y: axis.y * sin,
+        z: axis.z * sin,
+    };
+
+    let rotated = multiply_quaternions(delta, Quaternion::from(orientation));
+    Orientation::from(normalize_quaternion(rotated))
+}
 
-        rigidbody.linear_drag = 20.0;
-        rigidbody.angular_drag = 20.0;
+/// Calculates the shortest rotation from `target` to `orientation` as a rotation vector in world
+/// space.
+///
+/// The rotation vector is the axis of rotation scaled by the angle in radians, and is always in
+/// the range [0, PI], such that `rotate_orientation(target, offset)` gives `orientation`.
+pub fn orientation_offset(orientation: Orientation, target: Orientation) -> Vector3 {
+    let target = Quaternion::from(target);
+    let inverse_target = Quaternion {
+        w: target.w,
+        x: -target.x,
+        y: -target.y,
+        z: -target.z,
+    };
+    let difference = multiply_quaternions(Quaternion::from(orientation), inverse_target);
+
+    // `q` and `-q` represent the same rotation, but one of them goes the long way around. A
+    // non-negative `w` means a rotation of at most half a turn.
+    let sign = if difference.w < 0.0 { -1.0 } else { 1.0 };
+    let axis = Vector3::new(difference.x, difference.y, difference.z) * sign;
+    let sin_half_angle = axis.magnitude();
+    if sin_half_angle <= ::std::f32::EPSILON {
+        return Vector3::zero();
+    }
 
-        // Calculate the force based on the offset from equilibrium (the origin).
-        let offset = transform.position() - self.target_position;
-        let spring = -self.linear_spring * offset;
-        rigidbody.apply_force(spring);
+    // `atan2` stays accurate for small angles, where `acos` of `w` loses precision.
+    let angle = 2.0 * sin_half_angle.atan2(difference.w * sign);
+    axis / sin_half_angle * angle
+}
 
-        // Calculate torque.
-        let offset = (transform.orientation() - self.target_orientation).as_eulers();
-        let torque = -self.angular_spring * offset;
-        rigidbody.apply_torque(torque);
+fn multiply_quaternions(a: Quaternion, b: Quaternion) -> Quaternion {
+    Quaternion {
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+    }
+}
+
+fn normalize_quaternion(quaternion: Quaternion) -> Quaternion {
+    let Quaternion { w, x, y, z } = quaternion;
+    let magnitude = (w * w + x * x + y * y + z * z).sqrt();
+
+    Quaternion {
+        w: w / magnitude,
+        x: x / magnitude,
+        y: y / magnitude,
+        z: z / magnitude,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gunship::math::*;
+    use super::*;
+
+    /// The physics rate the player runs at.
+    const STEP: f32 = 1.0 / 120.0;
+
+    /// How far the spring starts stretched, in meters.
+    const START_OFFSET: f32 = 0.1;
+
+    /// Builds a spring with the same stiffness as the gun's, but with the specified damping.
+    fn gun_spring(damping_ratio: f32) -> Spring {
+        Spring {
+            damping_ratio: damping_ratio,
+            .. GunPhysics::default().linear_spring
+        }
+    }
+
+    /// Releases a unit mass from `START_OFFSET` on `spring` and steps it `steps` times, returning
+    /// the total energy left in the system.
+    fn spring_energy(integrator: Integrator, spring: Spring, steps: usize) -> f32 {
+        let mass = 1.0;
+        let damping = spring.damping(mass);
+
+        let mut position = Vector3::new(START_OFFSET, 0.0, 0.0);
+        let mut velocity = Vector3::zero();
+        for _ in 0..steps {
+            let start = position;
+            let (displacement, end_velocity) = integrator.integrate(velocity, STEP, |offset, velocity| {
+                ((start + offset) * -spring.stiffness - velocity * damping) / mass
+            });
+
+            position = position + displacement;
+            velocity = end_velocity;
+        }
+
+        0.5 * mass * velocity.dot(velocity) + 0.5 * spring.stiffness * position.dot(position)
+    }
+
+    fn start_energy(spring: Spring) -> f32 {
+        0.5 * spring.stiffness * START_OFFSET * START_OFFSET
+    }
+
+    /// The energy of a lightly damped spring decays at `2 * damping_ratio * natural_frequency`.
+    fn exact_energy(spring: Spring, steps: usize) -> f32 {
+        let frequency = spring.stiffness.sqrt();
+        let time = STEP * steps as f32;
+        start_energy(spring) * (-2.0 * spring.damping_ratio * frequency * time).exp()
+    }
+
+    #[test]
+    fn explicit_euler_gains_energy() {
+        let spring = gun_spring(0.01);
+        let energy = spring_energy(Integrator::ExplicitEuler, spring, 240);
+        assert!(energy > 10.0 * start_energy(spring), "energy = {}", energy);
+    }
+
+    #[test]
+    fn stable_integrators_decay_with_damping() {
+        let spring = gun_spring(0.01);
+        let exact = exact_energy(spring, 240);
+        let explicit = spring_energy(Integrator::ExplicitEuler, spring, 240);
+
+        for &(integrator, tolerance) in &[
+            (Integrator::SymplecticEuler, 0.1),
+            (Integrator::VelocityVerlet, 0.05),
+            (Integrator::Rk4, 0.05),
+        ] {
+            let energy = spring_energy(integrator, spring, 240);
+            assert!(energy < start_energy(spring), "{:?}: energy = {}", integrator, energy);
+            assert!(energy < explicit, "{:?}: energy = {}", integrator, energy);
+
+            let drift = (energy / exact - 1.0).abs();
+            assert!(drift < tolerance, "{:?}: drift = {}", integrator, drift);
+        }
+    }
+
+    #[test]
+    fn stable_integrators_bound_energy_without_damping() {
+        let spring = gun_spring(0.0);
+        let start = start_energy(spring);
+        let explicit = spring_energy(Integrator::ExplicitEuler, spring, 600);
+        assert!(explicit > 2.0 * start, "energy = {}", explicit);
+
+        for &(integrator, tolerance) in &[
+            (Integrator::SymplecticEuler, 0.15),
+            (Integrator::VelocityVerlet, 0.02),
+            (Integrator::Rk4, 0.01),
+        ] {
+            let energy = spring_energy(integrator, spring, 600);
+            let drift = (energy / start - 1.0).abs();
+            assert!(drift < tolerance, "{:?}: drift = {}", integrator, drift);
+        }
+    }
+
+    #[test]
+    fn gun_spring_settles_under_every_stable_integrator() {
+        // The spring exactly as the gun physics sets it up.
+        let spring = GunPhysics::default().linear_spring;
+        for &integrator in &[Integrator::SymplecticEuler, Integrator::VelocityVerlet, Integrator::Rk4] {
+            let energy = spring_energy(integrator, spring, 120);
+            assert!(energy < 1e-3 * start_energy(spring), "{:?}: energy = {}", integrator, energy);
+        }
+    }
+
+    /// The furthest the player can look up or down.
+    const PITCH_LIMIT: f32 = 0.45 * PI;
+
+    /// Builds the player's orientation the same way `Player::update()` does.
+    fn player_orientation(pitch: f32, yaw: f32) -> Orientation {
+        Orientation::from_eulers(0.0, yaw, 0.0) + Orientation::from_eulers(pitch, 0.0, 0.0)
+    }
+
+    #[test]
+    fn orientation_offset_is_zero_when_matching() {
+        for &pitch in &[-PITCH_LIMIT, PITCH_LIMIT] {
+            for index in 0..16 {
+                let yaw = TAU * index as f32 / 16.0 - PI;
+                let orientation = player_orientation(pitch, yaw);
+                let offset = orientation_offset(orientation, orientation);
+                assert!(offset.magnitude() == 0.0, "pitch = {}, yaw = {}, offset = {:?}", pitch, yaw, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn orientation_offset_is_continuous_at_pitch_limit() {
+        let perturbations = [
+            Vector3::new(0.01, 0.0, 0.0),
+            Vector3::new(0.0, 0.01, 0.0),
+            Vector3::new(0.0, 0.0, 0.01),
+            Vector3::new(-0.005, 0.005, 0.005),
+        ];
+
+        for &pitch in &[-PITCH_LIMIT, PITCH_LIMIT] {
+            for &perturbation in &perturbations {
+                let mut previous: Option<Vector3> = None;
+                for index in 0..64 {
+                    let yaw = TAU * index as f32 / 64.0 - PI;
+                    let target = player_orientation(pitch, yaw);
+                    let orientation = rotate_orientation(target, perturbation);
+                    let offset = orientation_offset(orientation, target);
+
+                    // The offset is the world space rotation that was applied, so it shouldn't
+                    // depend on where the player is looking.
+                    let error = (offset - perturbation).magnitude();
+                    assert!(
+                        error < 1e-4,
+                        "pitch = {}, yaw = {}, offset = {:?}, expected {:?}",
+                        pitch, yaw, offset, perturbation,
+                    );
+
+                    if let Some(previous) = previous {
+                        assert!(offset.dot(previous) > 0.0, "offset flipped at pitch = {}, yaw = {}", pitch, yaw);
+                        assert!((offset - previous).magnitude() < 1e-4, "offset jumped at pitch = {}, yaw = {}", pitch, yaw);
+                    }
+                    previous = Some(offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn orientation_offset_is_continuous_across_pitch_limit() {
+        // Pitching just past the clamp shouldn't make the offset jump, which is what happened
+        // with the old euler angle difference.
+        let perturbation = Vector3::new(0.0, 0.01, 0.0);
+        for &limit in &[-PITCH_LIMIT, PITCH_LIMIT] {
+            let offsets: Vec<Vector3> = [limit - 0.01, limit, limit + 0.01]
+                .iter()
+                .map(|&pitch| {
+                    let target = player_orientation(pitch, 1.0);
+                    orientation_offset(rotate_orientation(target, perturbation), target)
+                })
+                .collect();
+
+            for pair in offsets.windows(2) {
+                assert!(pair[0].dot(pair[1]) > 0.0, "offset flipped near pitch = {}", limit);
+                assert!((pair[0] - pair[1]).magnitude() < 1e-4, "offset jumped near pitch = {}", limit);
+            }
+        }
     }
 }
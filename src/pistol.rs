@@ -0,0 +1,493 @@
+use bore::{Bore, Firing};
+use bullet::BulletPool;
+use cartridge::CartridgeSpec;
+use collision::Shape;
+use gun::Cartridge;
+use gunship::*;
+use gunship::math::*;
+use gunship::mesh_renderer::MeshRenderer;
+use gunship::resource::Mesh;
+use gunship::transform::Transform;
+use magazine::Magazine;
+use physics::{InertiaTensor, Integrator, Rigidbody};
+use recoil::Recoil;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use weapon::{ActionEvent, Weapon};
+
+/// How far (in meters) the slide travels back when the pistol cycles.
+const SLIDE_TRAVEL: f32 = 0.03;
+
+/// How quickly the slide returns to battery. The remaining distance decays exponentially at this
+/// rate, so higher values are snappier.
+const SLIDE_RETURN_RATE: f32 = 40.0;
+
+/// The vertical spacing (in meters) between cartridges stacked in the magazine.
+const CARTRIDGE_SPACING: f32 = 0.01;
+
+/// The speed (in meters per second) at which the extractor flings cartridges out of the ejection
+/// port.
+const EJECT_SPEED: f32 = 3.0;
+
+/// A semi-automatic, striker-fired pistol fed from a detachable magazine.
+///
+/// Firing uses the recoil to cycle the slide, which ejects the spent case, strips the next round
+/// off the magazine into the chamber, and resets the striker. If the magazine is empty the slide
+/// locks back instead. The slide can also be racked by hand.
+#[derive(Debug)]
+pub struct Pistol {
+    pub transform: Transform,
+    pub mesh_renderer: MeshRenderer,
+    pub rigidbody: Rigidbody,
+    pub recoil: Recoil,
+
+    /// The chamberings of the cartridges the gun can be loaded with.
+    pub chamberings: Vec<String>,
+
+    pub slide_transform: Transform,
+    pub slide_renderer: MeshRenderer,
+
+    /// The pistol's magazine. It's either seated in the grip or in the player's other hand.
+    magazine: Magazine,
+    magazine_inserted: bool,
+    chamber: Option<Cartridge>,
+
+    striker_cocked: bool,
+    slide_locked: bool,
+    safety: bool,
+
+    /// The trigger has to be released before it can fire again.
+    trigger_reset: bool,
+
+    /// Whether the player was racking the slide last frame, so that holding the button only racks
+    /// once.
+    racking: bool,
+
+    /// How far back (in meters) the slide currently is.
+    slide_position: f32,
+
+    slide_offset: Vector3,
+    chamber_offset: Vector3,
+    magazine_offset: Vector3,
+    magazine_out_offset: Vector3,
+
+    pub bore: Bore,
+
+    events: Vec<ActionEvent>,
+    ejected: Vec<Cartridge>,
+
+    bullets: Arc<Mutex<BulletPool>>,
+}
+
+impl Pistol {
+    pub fn new(
+        mesh: &Mesh,
+        slide_mesh: &Mesh,
+        bullets: Arc<Mutex<BulletPool>>,
+        magazine_capacity: usize,
+        start_pos: Point,
+        start_orientation: Orientation,
+    ) -> Pistol {
+        let mut transform = Transform::new();
+        transform.set_position(start_pos);
+        transform.set_orientation(start_orientation);
+        let mesh_renderer = MeshRenderer::new(&mesh, &transform);
+        let mut rigidbody = Rigidbody::new();
+        rigidbody.integrator = Integrator::VelocityVerlet;
+
+        // Approximate the gun as a solid box roughly the size of the mesh.
+        rigidbody.mass = 0.8;
+        rigidbody.inertia = InertiaTensor::from_shape(
+            &Shape::Obb { half_extents: Vector3::new(0.015, 0.07, 0.09) },
+            rigidbody.mass,
+        );
+        rigidbody.teleport(start_pos, start_orientation);
+
+        let mut slide_transform = Transform::new();
+        slide_transform.set_position(start_pos);
+        slide_transform.set_scale(Vector3::new(0.025, 0.03, 0.18));
+        let slide_renderer = MeshRenderer::new(&slide_mesh, &slide_transform);
+
+        Pistol {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+            rigidbody: rigidbody,
+            recoil: Recoil {
+                bore_offset: Vector3::new(0.0, 0.05, -0.1),
+                .. Recoil::default()
+            },
+
+            chamberings: vec!["9mm_luger".into()],
+
+            slide_transform: slide_transform,
+            slide_renderer: slide_renderer,
+
+            magazine: Magazine::new(magazine_capacity),
+            magazine_inserted: true,
+            chamber: None,
+
+            striker_cocked: false,
+            slide_locked: false,
+            safety: false,
+
+            trigger_reset: true,
+            racking: false,
+
+            slide_position: 0.0,
+
+            slide_offset: Vector3::new(0.0, 0.05, -0.02),
+            chamber_offset: Vector3::new(0.0, 0.05, -0.04),
+            magazine_offset: Vector3::new(0.0, 0.03, 0.0),
+            magazine_out_offset: Vector3::new(-0.08, -0.1, 0.0),
+
+            bore: Bore::new(Vector3::new(0.0, 0.05, -0.11)),
+
+            events: Vec::new(),
+            ejected: Vec::new(),
+
+            bullets: bullets,
+        }
+    }
+
+    /// Takes all action events that have happened since the last call.
+    pub fn drain_events(&mut self) -> Vec<ActionEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Takes every cartridge the slide has thrown out since the last call.
+    pub fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        mem::replace(&mut self.ejected, Vec::new())
+    }
+
+    pub fn magazine(&self) -> &Magazine {
+        &self.magazine
+    }
+
+    pub fn is_magazine_inserted(&self) -> bool {
+        self.magazine_inserted
+    }
+
+    pub fn is_slide_locked(&self) -> bool {
+        self.slide_locked
+    }
+
+    pub fn chamber(&self) -> &Option<Cartridge> {
+        &self.chamber
+    }
+
+    /// Pulls the trigger, firing if the striker is set on a live round.
+    ///
+    /// The trigger has to be released with `release_trigger()` before it can fire again.
+    pub fn pull_trigger(&mut self) {
+        if !self.trigger_reset {
+            return;
+        }
+        self.trigger_reset = false;
+
+        // The striker can't be released again until a hang-fire has gone off or been cleared.
+        if self.safety || self.slide_locked || self.bore.is_hanging_fire() {
+            self.events.push(ActionEvent::TriggerBlocked);
+            return;
+        }
+
+        // After a dry fire the striker stays down until the slide is racked, so the trigger is
+        // dead.
+        if !self.striker_cocked {
+            return;
+        }
+        self.striker_cocked = false;
+
+        let event = match self.chamber.as_mut() {
+            Some(cartridge) => {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                self.bore.strike(cartridge, &mut firing)
+            },
+            None => ActionEvent::DryFired,
+        };
+        self.fired(event);
+    }
+
+    /// Counts down a pending hang-fire, firing the cartridge if it's still chambered once it goes
+    /// off.
+    pub fn update(&mut self, delta: f32) {
+        if !self.bore.update(delta) {
+            return;
+        }
+
+        let event = match self.chamber.as_mut() {
+            Some(cartridge) => {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                self.bore.discharge(cartridge, &mut firing)
+            },
+            None => return,
+        };
+        self.fired(event);
+    }
+
+    /// Pushes a rod down the barrel, knocking out a stuck bullet.
+    ///
+    /// The barrel can only be reached with the slide locked back or the magazine out.
+    pub fn clear_bore(&mut self) {
+        if (self.slide_locked || !self.magazine_inserted) && self.bore.clear() {
+            self.events.push(ActionEvent::BoreCleared);
+        }
+    }
+
+    /// Resets the trigger so that it can fire again.
+    pub fn release_trigger(&mut self) {
+        self.trigger_reset = true;
+    }
+
+    /// Racks the slide by hand.
+    ///
+    /// If the slide is locked back this releases it instead, chambering a round from the magazine.
+    pub fn rack_slide(&mut self) {
+        if self.slide_locked {
+            self.release_slide();
+        } else {
+            self.events.push(ActionEvent::SlideRacked);
+            self.cycle_slide();
+        }
+    }
+
+    /// Releases the slide from lock, letting it run forward and chamber a round.
+    pub fn release_slide(&mut self) {
+        if self.slide_locked {
+            self.slide_locked = false;
+            self.feed();
+            self.events.push(ActionEvent::SlideReleased);
+        }
+    }
+
+    /// Toggles the manual safety.
+    pub fn toggle_safety(&mut self) {
+        self.safety = !self.safety;
+        self.events.push(if self.safety { ActionEvent::SafetyEngaged } else { ActionEvent::SafetyDisengaged });
+    }
+
+    /// Drops the magazine out of the grip into the player's other hand.
+    pub fn remove_magazine(&mut self) {
+        if self.magazine_inserted {
+            self.magazine_inserted = false;
+            self.events.push(ActionEvent::MagazineRemoved);
+        }
+    }
+
+    /// Seats the magazine back in the grip.
+    ///
+    /// A locked slide stays locked until it's released or racked.
+    pub fn insert_magazine(&mut self) {
+        if !self.magazine_inserted {
+            self.magazine_inserted = true;
+            self.events.push(ActionEvent::MagazineInserted);
+        }
+    }
+
+    /// Loads a cartridge into the magazine while it's out of the gun.
+    ///
+    /// If the magazine is seated and the slide is locked back on an empty chamber, the cartridge
+    /// is dropped straight into the chamber instead.
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if !cartridge.spec.fits(&self.chamberings) {
+            return Err(cartridge);
+        }
+
+        if !self.magazine_inserted {
+            return self.magazine.push(cartridge);
+        }
+
+        if self.slide_locked && self.chamber.is_none() {
+            self.chamber = Some(cartridge);
+            return Ok(());
+        }
+
+        Err(cartridge)
+    }
+
+    /// Gets the speed and direction cartridges are thrown when ejected, in world space.
+    pub fn eject_velocity(&self) -> Vector3 {
+        // The ejection port is on the right side of the slide, and throws cartridges up and back.
+        let direction = Vector3::new(1.0, 0.5, 0.3).normalized();
+        self.rigidbody.velocity() + self.transform.orientation() * (direction * EJECT_SPEED)
+    }
+
+    /// Reports what happened when the striker hit the chambered round.
+    ///
+    /// Only a full-power shot has enough recoil to cycle the slide. Anything else leaves the round
+    /// in the chamber until the slide is racked by hand.
+    fn fired(&mut self, event: ActionEvent) {
+        let cycles = match event {
+            ActionEvent::Fired(_) | ActionEvent::BoreObstructed => true,
+            _ => false,
+        };
+
+        self.events.push(event);
+        if cycles {
+            self.cycle_slide();
+        }
+    }
+
+    /// Runs the slide back and forward, ejecting whatever's in the chamber and feeding the next
+    /// round.
+    fn cycle_slide(&mut self) {
+        self.slide_position = SLIDE_TRAVEL;
+        self.striker_cocked = true;
+
+        if let Some(mut cartridge) = self.chamber.take() {
+            self.bore.extract(&mut cartridge);
+            self.ejected.push(cartridge);
+        }
+
+        // The magazine follower pushes up the slide stop once the magazine is empty.
+        if self.magazine_inserted && self.magazine.is_empty() {
+            self.slide_locked = true;
+            self.events.push(ActionEvent::SlideLocked);
+            return;
+        }
+
+        self.feed();
+    }
+
+    /// Strips the top round off the magazine into the chamber.
+    fn feed(&mut self) {
+        if self.magazine_inserted && self.chamber.is_none() {
+            self.chamber = self.magazine.pop();
+        }
+    }
+
+    pub fn update_transforms(&mut self) {
+        // The slide snaps back under recoil and springs forward again, unless it's locked back.
+        let target = if self.slide_locked { SLIDE_TRAVEL } else { 0.0 };
+        let fraction = 1.0 - (-SLIDE_RETURN_RATE * time::delta_f32()).exp();
+        self.slide_position += (target - self.slide_position) * fraction;
+
+        let orientation = self.transform.orientation();
+        let slide_offset = self.slide_offset + Vector3::new(0.0, 0.0, self.slide_position);
+        self.slide_transform.set_position(self.transform.position() + orientation * slide_offset);
+        self.slide_transform.set_orientation(orientation);
+
+        if let Some(cartridge) = self.chamber.as_mut() {
+            cartridge.transform.set_position(self.transform.position() + orientation * self.chamber_offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+
+        // Stack the magazine's cartridges down from the top of the magazine.
+        let magazine_offset = if self.magazine_inserted { self.magazine_offset } else { self.magazine_out_offset };
+        for (index, cartridge) in self.magazine.iter_mut().rev().enumerate() {
+            let offset = magazine_offset - Vector3::new(0.0, CARTRIDGE_SPACING * index as f32, 0.0);
+            cartridge.transform.set_position(self.transform.position() + orientation * offset);
+            cartridge.transform.set_orientation(orientation);
+        }
+    }
+}
+
+impl Weapon for Pistol {
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn rigidbody(&self) -> &Rigidbody {
+        &self.rigidbody
+    }
+
+    fn rigidbody_mut(&mut self) -> &mut Rigidbody {
+        &mut self.rigidbody
+    }
+
+    fn recoil(&self) -> &Recoil {
+        &self.recoil
+    }
+
+    fn pull_trigger(&mut self) {
+        Pistol::pull_trigger(self);
+    }
+
+    fn release_trigger(&mut self) {
+        Pistol::release_trigger(self);
+    }
+
+    /// Racks the slide when the button is pressed.
+    fn secondary_action(&mut self, held: bool) {
+        if held && !self.racking {
+            self.rack_slide();
+        }
+
+        self.racking = held;
+    }
+
+    fn safety(&mut self) {
+        self.toggle_safety();
+    }
+
+    /// The magazine being out counts as the action being open, since that's when it's loaded.
+    fn is_action_open(&self) -> bool {
+        !self.magazine_inserted
+    }
+
+    fn open_action(&mut self) {
+        self.remove_magazine();
+    }
+
+    fn close_action(&mut self) {
+        self.insert_magazine();
+    }
+
+    /// Releases the slide if it's locked back.
+    fn cycle(&mut self, _amount: isize) {
+        self.release_slide();
+    }
+
+    fn accepts(&self, spec: &CartridgeSpec) -> bool {
+        spec.fits(&self.chamberings)
+    }
+
+    fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        Pistol::load_cartridge(self, cartridge)
+    }
+
+    /// Empties the magazine while it's out of the gun.
+    fn unload(&mut self) -> Vec<Cartridge> {
+        if self.magazine_inserted {
+            return Vec::new();
+        }
+
+        self.magazine.take_all()
+    }
+
+    /// Thumbs the top cartridge off the magazine while it's out of the gun.
+    fn unload_one(&mut self) -> Option<Cartridge> {
+        if self.magazine_inserted {
+            return None;
+        }
+
+        self.magazine.pop()
+    }
+
+    fn eject_velocity(&self) -> Vector3 {
+        Pistol::eject_velocity(self)
+    }
+
+    fn clear_bore(&mut self) {
+        Pistol::clear_bore(self);
+    }
+
+    fn update(&mut self, delta: f32) {
+        Pistol::update(self, delta);
+    }
+
+    fn drain_events(&mut self) -> Vec<ActionEvent> {
+        Pistol::drain_events(self)
+    }
+
+    fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        Pistol::drain_ejected(self)
+    }
+
+    fn interpolate(&mut self, alpha: f32) {
+        self.rigidbody.interpolate(&mut self.transform, alpha);
+    }
+
+    fn update_transforms(&mut self) {
+        Pistol::update_transforms(self);
+    }
+}
@@ -1,47 +1,117 @@
+use cartridge::CartridgeSpec;
+use collision::*;
 use gun::*;
 use gunship::*;
 use gunship::camera::Camera;
 use gunship::input::*;
 use gunship::math::*;
-use gunship::mesh_renderer::MeshRenderer;
 use gunship::resource::Mesh;
 use gunship::transform::Transform;
+use inventory::{AmmoPickup, Inventory};
 use physics::*;
-use std::sync::Arc;
+use recoil::*;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use weapon::{ActionEvent, Weapon};
 
 const ACCELERATION: f32 = 50.0;
 const MAX_SPEED: f32 = 5.0;
 
+/// Where (relative to the player) holstered weapons are carried.
+const HOLSTER_OFFSET: Vector3 = Vector3 { x: 0.25, y: -0.7, z: 0.0 };
+
+/// How far apart (in meters) holstered weapons are spaced along the player's side.
+const HOLSTER_SPACING: f32 = 0.15;
+
+/// The camera's field of view (in radians) when it isn't looking through a scope.
+const FIELD_OF_VIEW: f32 = 1.0472;
+
+/// How far (in radians) the camera can pitch up or down from level.
+const PITCH_LIMIT: f32 = 0.45 * PI;
+
+/// Where (relative to the player) the gun is held while aiming down the sights, lined up with the
+/// player's eye.
+const AIM_OFFSET: Vector3 = Vector3 { x: 0.0, y: -0.05, z: -0.25 };
+
+/// Where (relative to the player) the gun is held while inspecting it, close enough to the camera
+/// to see into the chambers.
+const INSPECT_OFFSET: Vector3 = Vector3 { x: 0.05, y: -0.08, z: -0.18 };
+
+/// How far (in radians) the gun is tipped up and rolled over while inspecting it, so that the
+/// back of the cylinder faces the camera.
+const INSPECT_PITCH: f32 = 0.5;
+const INSPECT_ROLL: f32 = 0.9;
+
+/// Where (relative to the player) the player's feet are, which is where they pick up and drop
+/// ammo.
+const FEET_OFFSET: Vector3 = Vector3 { x: 0.0, y: -0.8, z: 0.0 };
+
+/// How close (in meters) the player's feet have to get to ammo lying in the world to pick it up.
+const PICKUP_RADIUS: f32 = 0.75;
+
 #[derive(Debug)]
 pub struct Player {
     pub camera: Camera,
     pub transform: Transform,
     pub rigidbody: Rigidbody,
+    pub collider: ColliderId,
+    pub timestep: FixedTimestep,
+
+    pub gun: Box<dyn Weapon>,
+
+    /// The weapons the player is carrying but not holding. Switching weapons cycles through
+    /// them in order.
+    pub holstered: Vec<Box<dyn Weapon>>,
 
-    pub gun: Revolver,
     pub gun_physics: GunPhysics,
 
+    /// Where (relative to the player) the gun is held when not aiming.
+    pub hip_offset: Vector3,
+
     pub pitch: f32,
     pub yaw: f32,
+    pub camera_kick: CameraKick,
+
+    /// The ammo the player is carrying. Guns are loaded from it, and live cartridges that come
+    /// out of the gun go back into it.
+    pub inventory: Inventory,
+
+    /// Ammo lying in the world, including any live cartridges the player had no room to keep.
+    pub pickups: Vec<AmmoPickup>,
+    pub pickup_mesh: Arc<Mesh>,
 
-    pub cartridge_mesh: Arc<Mesh>,
+    /// Spent casings ejected from the gun.
+    pub casings: Vec<Casing>,
+
+    /// The types of cartridge the player can load, and which one they currently have selected.
+    pub cartridge_specs: Vec<Arc<CartridgeSpec>>,
+    pub cartridge_spec: usize,
+
+    pub collision_world: Arc<Mutex<CollisionWorld>>,
 }
 
 impl Player {
     pub fn update(&mut self) {
-        // Cache off the position and rotation and then drop the transform
-        // so that we don't have multiple borrows of transform_manager.
+        // Looking through a scope magnifies mouse movement as much as the view, so slow it down
+        // to match.
+        let sensitivity = PI * 0.1 / self.gun.magnification();
         let (movement_x, movement_y) = input::mouse_delta();
-        self.yaw += (-movement_x as f32) * PI * 0.1 * time::delta_f32();
-        self.pitch += (-movement_y as f32) * PI * 0.1 * time::delta_f32();
-        self.pitch = self.pitch.clamp(-0.45 * PI, 0.45 * PI);
+        self.yaw += (-movement_x as f32) * sensitivity * time::delta_f32();
+        self.pitch += (-movement_y as f32) * sensitivity * time::delta_f32();
+
+        // Return the camera from any recoil kick.
+        let (kick_pitch, kick_yaw) = self.camera_kick.update(time::delta_f32());
+        self.pitch += kick_pitch;
+        self.yaw += kick_yaw;
+
+        self.pitch = self.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
 
         // Set orientation by applying yaw first, then pitch. If we do both at once (e.g.
         // `Orientation::from_eulers(pitch, yaw, 0.0)`) then pitch is applied first, which causes
         // pitch to invert with the player turns around.
-        self.transform.set_orientation(
-            Orientation::from_eulers(0.0, self.yaw, 0.0) + Orientation::from_eulers(self.pitch, 0.0, 0.0),
-        );
+        let orientation = Orientation::from_eulers(0.0, self.yaw, 0.0) + Orientation::from_eulers(self.pitch, 0.0, 0.0);
+        self.transform.set_orientation(orientation);
+        self.rigidbody.set_orientation(orientation);
 
         // Handle movement through root entity.
         {
@@ -84,46 +154,282 @@ impl Player {
             self.rigidbody.set_velocity(velocity);
         };
 
-        self.rigidbody.update(&mut self.transform);
-        self.gun_physics.update_target(&self.transform);
-        self.gun_physics.update(&mut self.gun.rigidbody, &self.gun.transform);
-        self.gun.rigidbody.update(&mut self.gun.transform);
+        // Run physics at a fixed rate, independent of the frame rate.
+        let steps = self.timestep.advance(time::delta_f32());
+        for _ in 0..steps {
+            let delta = self.timestep.step;
+
+            self.rigidbody.step(delta);
+            for casing in &mut self.casings {
+                casing.step(delta);
+            }
+
+            // Push the player out of anything they walked into, and bounce casings off of
+            // whatever they hit.
+            {
+                let mut world = self.collision_world.lock().unwrap();
+                // The rigidbody is pitched along with the camera, but the player's body stays
+                // upright, so only the yaw is passed on to the capsule.
+                {
+                    let collider = world.get_mut(self.collider).unwrap();
+                    collider.sync(&self.rigidbody);
+                    collider.orientation = Orientation::from_eulers(0.0, self.yaw, 0.0);
+                }
+                for casing in &self.casings {
+                    world.get_mut(casing.collider).unwrap().sync(&casing.rigidbody);
+                }
+
+                world.step();
+
+                for contact in world.contacts_with(self.collider) {
+                    self.rigidbody.resolve_contact(&contact);
+                }
+
+                for casing in &mut self.casings {
+                    casing.resolve_contacts(&world);
+                }
+            }
+
+            self.gun_physics.update_target(&self.rigidbody);
+            self.gun_physics.update(self.gun.rigidbody_mut());
+            self.gun.rigidbody_mut().step(delta);
+        }
+
+        // Clean up casings that have been lying around for a while.
+        if self.casings.iter().any(Casing::is_expired) {
+            let mut world = self.collision_world.lock().unwrap();
+            self.casings.retain(|casing| {
+                if casing.is_expired() {
+                    world.remove(casing.collider);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        // Pick up any ammo the player walks over, leaving behind whatever doesn't fit.
+        if !self.pickups.is_empty() {
+            let position = self.rigidbody.position() + FEET_OFFSET;
+            for pickup in &mut self.pickups {
+                if (pickup.transform.position() - position).magnitude() <= PICKUP_RADIUS {
+                    self.inventory.pick_up(pickup);
+                }
+            }
+            self.pickups.retain(|pickup| !pickup.is_empty());
+        }
+
+        // Interpolate the rendered transforms between the last two physics steps. The camera's
+        // orientation comes directly from mouse input, so we restore it after interpolating.
+        let alpha = self.timestep.alpha();
+        self.rigidbody.interpolate(&mut self.transform, alpha);
+        self.transform.set_orientation(orientation);
+        self.gun.interpolate(alpha);
+        for casing in &mut self.casings {
+            casing.interpolate(alpha);
+        }
+
+        // Holstered weapons ride along on the player's hip, pointing down.
+        let hip_orientation = Orientation::from_eulers(0.0, self.yaw, 0.0);
+        let holster_orientation = hip_orientation + Orientation::from_eulers(-0.5 * PI, 0.0, 0.0);
+        for (index, weapon) in self.holstered.iter_mut().enumerate() {
+            let offset = HOLSTER_OFFSET + Vector3::new(0.0, 0.0, HOLSTER_SPACING * index as f32);
+            let position = self.transform.position() + hip_orientation * offset;
+            weapon.rigidbody_mut().teleport(position, holster_orientation);
+            weapon.rigidbody_mut().set_velocity(self.rigidbody.velocity());
+            weapon.update(time::delta_f32());
+            weapon.interpolate(1.0);
+            weapon.update_transforms();
+        }
+
+        // Swap the weapon in hand for the next one in the holster. The gun physics pulls the new
+        // weapon up into the player's hand.
+        if input::key_pressed(ScanCode::G) && !self.holstered.is_empty() {
+            let next = self.holstered.remove(0);
+            let previous = mem::replace(&mut self.gun, next);
+            self.holstered.push(previous);
+        }
 
         if input::mouse_scroll() != 0 {
-            self.gun.rotate_cylinder(input::mouse_scroll() as isize);
+            self.gun.cycle(input::mouse_scroll() as isize);
         }
 
         if input::key_pressed(ScanCode::F) {
-            self.gun.rotate_cylinder(1);
+            self.gun.cycle(1);
         }
 
         if input::key_pressed(ScanCode::R) {
-            // Create the cartridge.
-            let mut cartridge_transform = Transform::new();
-            cartridge_transform.set_scale(Vector3::new(0.01, 0.01, 0.03));
+            let cartridge = self.loadable_spec().and_then(|spec| self.inventory.take_cartridge(&*spec.name));
+            if let Some(cartridge) = cartridge {
+                // TODO: Animate cartridge being inserted.
+                // TODO: Animate failure when cartidge doesn't go in.
+                if let Err(cartridge) = self.gun.load_cartridge(cartridge) {
+                    self.stow_cartridges(vec![cartridge]);
+                }
+            }
+        }
 
-            let cartridge_renderer = MeshRenderer::new(&self.cartridge_mesh, &cartridge_transform);
+        if input::key_pressed(ScanCode::L) {
+            // Load from the fullest speedloader, and put back whatever the gun didn't take.
+            let gun = &self.gun;
+            if let Some(index) = self.inventory.ready_speedloader(|spec| gun.accepts(spec)) {
+                let cartridges = self.inventory.take_speedloader(index);
+                let rejected = self.gun.load_clip(cartridges);
+                let remaining = self.inventory.return_to_speedloader(index, rejected);
+                self.stow_cartridges(remaining);
+            }
+        }
 
-            // TODO: Animate cartridge being inserted.
-            // TODO: Animate failure when cartidge doesn't go in.
-            let _ = self.gun.load_cartridge(Cartridge {
-                transform: cartridge_transform,
-                mesh_renderer: cartridge_renderer,
+        if input::key_pressed(ScanCode::K) {
+            if let Some(spec) = self.loadable_spec() {
+                self.inventory.fill_speedloaders(&*spec.name);
+            }
+        }
 
-                has_fired: false,
-            });
+        // Select the next type of cartridge the gun in hand can be loaded with.
+        if input::key_pressed(ScanCode::T) {
+            let count = self.cartridge_specs.len();
+            for offset in 1..count + 1 {
+                let index = (self.cartridge_spec + offset) % count;
+                if self.gun.accepts(&self.cartridge_specs[index]) {
+                    self.cartridge_spec = index;
+                    break;
+                }
+            }
+        }
+
+        if input::key_pressed(ScanCode::O) {
+            if self.gun.is_action_open() {
+                self.gun.close_action();
+            } else {
+                self.gun.open_action();
+            }
+        }
+
+        if input::key_pressed(ScanCode::V) {
+            let velocity = self.gun.eject_velocity();
+            let cartridges = self.gun.unload();
+            self.collect_cartridges(cartridges, velocity);
+        }
+
+        if input::key_pressed(ScanCode::P) {
+            if let Some(cartridge) = self.gun.unload_one() {
+                let velocity = self.gun.rigidbody().velocity();
+                self.collect_cartridges(vec![cartridge], velocity);
+            }
+        }
+
+        self.gun.secondary_action(input::mouse_button_down(1));
+        self.gun.inspect(input::key_down(ScanCode::I));
+
+        if input::key_pressed(ScanCode::C) {
+            self.gun.safety();
+        }
+
+        if input::key_pressed(ScanCode::X) {
+            self.gun.decock();
         }
 
-        if input::mouse_button_pressed(1) {
-            self.gun.pull_hammer();
+        if input::key_pressed(ScanCode::B) {
+            self.gun.clear_bore();
         }
 
-        if input::mouse_button_pressed(0) && self.gun.fire() {
-            // Apply kickback animation.
-            self.gun.rigidbody.add_velocity(Vector3::new(0.0, 3.0, 10.0));
-            self.gun.rigidbody.add_angular_velocity(Vector3::new(15.0 * PI, -8.0 * PI, 5.0 * PI));
+        if input::mouse_button_pressed(0) {
+            self.gun.pull_trigger();
+        } else if !input::mouse_button_down(0) {
+            self.gun.release_trigger();
+        }
+
+        // A hang-fire can go off at any point after the trigger was pulled.
+        self.gun.update(time::delta_f32());
+
+        for event in self.gun.drain_events() {
+            match event {
+                ActionEvent::Fired(load) => {
+                    // Recoil has already been applied to the gun, but the camera kick is up to us.
+                    let (pitch, yaw) = self.gun.recoil().camera_kick(&load, self.gun.rigidbody().mass);
+                    let pitch_room = (PITCH_LIMIT + self.pitch, PITCH_LIMIT - self.pitch);
+                    let (pitch, yaw) = self.camera_kick.kick(pitch, yaw, pitch_room);
+                    self.pitch += pitch;
+                    self.yaw += yaw;
+                },
+
+                // TODO: Play audio for the rest of the action.
+                _ => {},
+            }
+        }
+
+        // Bring the gun up to the player's eye while aiming, and zoom in if it has a scope.
+        let aim = self.gun.aim();
+        let held_offset = self.hip_offset + (AIM_OFFSET - self.hip_offset) * aim;
+        self.camera.set_fov(FIELD_OF_VIEW / self.gun.magnification());
+
+        // Bring the gun up close and turn it over while inspecting it.
+        let inspection = self.gun.inspection();
+        self.gun_physics.position_offset = held_offset + (INSPECT_OFFSET - held_offset) * inspection;
+        self.gun_physics.held_orientation = Orientation::from_eulers(
+            INSPECT_PITCH * inspection,
+            0.0,
+            INSPECT_ROLL * inspection,
+        );
+
+        // Semi-automatics throw out cartridges on their own as they cycle.
+        let ejected = self.gun.drain_ejected();
+        if !ejected.is_empty() {
+            let velocity = self.gun.eject_velocity();
+            self.collect_cartridges(ejected, velocity);
         }
 
         self.gun.update_transforms();
     }
+
+    /// Gets the type of cartridge to load into the gun in hand.
+    ///
+    /// This is the selected type if the gun accepts it, otherwise the first type the gun accepts
+    /// that the player is carrying.
+    fn loadable_spec(&self) -> Option<Arc<CartridgeSpec>> {
+        let selected = &self.cartridge_specs[self.cartridge_spec];
+        if self.gun.accepts(selected) {
+            return Some(selected.clone());
+        }
+
+        self.cartridge_specs
+            .iter()
+            .find(|spec| self.gun.accepts(spec) && self.inventory.count(&*spec.name) > 0)
+            .cloned()
+    }
+
+    /// Takes cartridges that have come out of the gun. Spent casings fall to the ground with the
+    /// specified velocity, and live cartridges are stowed in the inventory.
+    fn collect_cartridges(&mut self, cartridges: Vec<Cartridge>, velocity: Vector3) {
+        let mut live = Vec::new();
+        {
+            let mut world = self.collision_world.lock().unwrap();
+            for cartridge in cartridges {
+                if cartridge.has_fired() {
+                    self.casings.push(Casing::new(cartridge, velocity, &mut world));
+                } else {
+                    live.push(cartridge);
+                }
+            }
+        }
+
+        self.stow_cartridges(live);
+    }
+
+    /// Puts live cartridges back in the inventory. Any that don't fit are dropped at the player's
+    /// feet so that they can be picked up again later.
+    fn stow_cartridges(&mut self, cartridges: Vec<Cartridge>) {
+        let mut dropped = Vec::new();
+        for cartridge in cartridges {
+            if let Err(cartridge) = self.inventory.store(cartridge) {
+                dropped.push(cartridge.spec.clone());
+            }
+        }
+
+        if !dropped.is_empty() {
+            let position = self.transform.position() + FEET_OFFSET;
+            self.pickups.push(AmmoPickup::new(&self.pickup_mesh, position, dropped));
+        }
+    }
 }
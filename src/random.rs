@@ -0,0 +1,45 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small, fast pseudo-random number generator (xorshift).
+///
+/// Not suitable for anything security related, but plenty good enough for spreading pellets.
+/// The same seed always produces the same sequence.
+#[derive(Debug, Clone, Copy)]
+pub struct Random {
+    state: u32,
+}
+
+impl Random {
+    pub fn new(seed: u32) -> Random {
+        // Xorshift gets stuck at zero, so nudge it to any other value.
+        Random {
+            state: if seed == 0 { 0x9e3779b9 } else { seed },
+        }
+    }
+
+    /// Creates a generator seeded from the system clock.
+    pub fn from_time() -> Random {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Random::new(now.subsec_nanos() ^ now.as_secs() as u32)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// Gets a value in the range [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        // Only use the top 24 bits so that every value is exactly representable.
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Gets a value in the range [min, max).
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
@@ -0,0 +1,136 @@
+use gunship::math::*;
+use physics::Rigidbody;
+
+/// How fast the propellant gases leave the muzzle relative to the bullet.
+///
+/// The gases leave the barrel faster than the bullet and contribute a significant portion of the
+/// total recoil. 1.5 is the usual rule of thumb for handgun cartridges.
+pub const GAS_VELOCITY_FACTOR: f32 = 1.5;
+
+/// The projectile and propellant of a cartridge, used to calculate recoil.
+#[derive(Debug, Clone, Copy)]
+pub struct Load {
+    /// Mass of the bullet in kilograms.
+    pub bullet_mass: f32,
+
+    /// Mass of the powder charge in kilograms.
+    pub powder_mass: f32,
+
+    /// Velocity of the bullet as it leaves the barrel in meters per second.
+    pub muzzle_velocity: f32,
+}
+
+impl Load {
+    /// Calculates the total momentum (in newton seconds) carried out of the barrel by the bullet
+    /// and propellant gases, which is equal to the recoil impulse on the gun.
+    pub fn momentum(&self) -> f32 {
+        self.bullet_mass * self.muzzle_velocity
+            + self.powder_mass * self.muzzle_velocity * GAS_VELOCITY_FACTOR
+    }
+}
+
+impl Default for Load {
+    /// A 158 grain .357 Magnum load.
+    fn default() -> Load {
+        Load {
+            bullet_mass: 0.01024,
+            powder_mass: 0.00097,
+            muzzle_velocity: 380.0,
+        }
+    }
+}
+
+/// Describes how a gun responds to being fired.
+#[derive(Debug, Clone, Copy)]
+pub struct Recoil {
+    /// The point (in the gun's local space) where the recoil impulse is applied.
+    ///
+    /// This should sit on the axis of the bore. The further the bore sits above the gun's center
+    /// of mass, the more the muzzle flips up when fired.
+    pub bore_offset: Vector3,
+
+    /// How much the camera pitches up (in radians) per meter per second of free recoil velocity.
+    ///
+    /// Set to 0 to disable camera kick.
+    pub camera_kick: f32,
+
+    /// How much the camera yaws relative to how much it pitches.
+    pub camera_kick_yaw: f32,
+}
+
+impl Recoil {
+    /// Applies the recoil from firing `load` to the gun's rigidbody.
+    pub fn apply(&self, load: &Load, rigidbody: &mut Rigidbody) {
+        // The bullet leaves along the gun's local forward axis, so the gun is pushed back along
+        // local +z.
+        let impulse = Vector3::new(0.0, 0.0, load.momentum());
+        rigidbody.apply_local_impulse_at_point(impulse, self.bore_offset);
+    }
+
+    /// Calculates how much the camera should kick for the shot, given the mass of the gun.
+    ///
+    /// Returns the pitch and yaw offsets in radians.
+    pub fn camera_kick(&self, load: &Load, gun_mass: f32) -> (f32, f32) {
+        let recoil_velocity = load.momentum() / gun_mass;
+        let pitch = recoil_velocity * self.camera_kick;
+        (pitch, pitch * self.camera_kick_yaw)
+    }
+}
+
+impl Default for Recoil {
+    fn default() -> Recoil {
+        Recoil {
+            bore_offset: Vector3::new(0.0, 0.04, -0.2),
+            camera_kick: 0.02,
+            camera_kick_yaw: 0.2,
+        }
+    }
+}
+
+/// Tracks how far the camera has been kicked by recoil and gradually returns it.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraKick {
+    /// How quickly the camera returns after a kick. The outstanding kick decays exponentially at
+    /// this rate, so higher values recover faster.
+    pub recovery_rate: f32,
+
+    pitch: f32,
+    yaw: f32,
+}
+
+impl CameraKick {
+    pub fn new(recovery_rate: f32) -> CameraKick {
+        CameraKick {
+            recovery_rate: recovery_rate,
+            pitch: 0.0,
+            yaw: 0.0,
+        }
+    }
+
+    /// Adds a kick, returning the pitch and yaw to immediately add to the camera.
+    ///
+    /// `pitch_room` is how far (in radians) the camera can still pitch down and up before it hits
+    /// its limits. Only the part of the kick that fits is applied and later recovered, otherwise
+    /// recovering would pull the camera past where the player was aiming.
+    pub fn kick(&mut self, pitch: f32, yaw: f32, pitch_room: (f32, f32)) -> (f32, f32) {
+        let (down, up) = pitch_room;
+        let pitch = pitch.clamp(-down, up);
+
+        self.pitch += pitch;
+        self.yaw += yaw;
+        (pitch, yaw)
+    }
+
+    /// Recovers part of the outstanding kick, returning the pitch and yaw to add to the camera.
+    ///
+    /// Only the kick is recovered, so any aiming the player did in the meantime is preserved.
+    pub fn update(&mut self, delta: f32) -> (f32, f32) {
+        let fraction = 1.0 - (-self.recovery_rate * delta).exp();
+        let pitch = self.pitch * fraction;
+        let yaw = self.yaw * fraction;
+
+        self.pitch -= pitch;
+        self.yaw -= yaw;
+        (-pitch, -yaw)
+    }
+}
@@ -0,0 +1,453 @@
+use bore::{Bore, Firing};
+use bullet::BulletPool;
+use cartridge::CartridgeSpec;
+use collision::Shape;
+use config::Config;
+use gun::Cartridge;
+use gunship::*;
+use gunship::math::*;
+use gunship::mesh_renderer::MeshRenderer;
+use gunship::resource::Mesh;
+use gunship::transform::Transform;
+use physics::{InertiaTensor, Integrator, Rigidbody};
+use recoil::Recoil;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use tween::{self, AngleTween};
+use weapon::{ActionEvent, Weapon};
+
+/// The angle (in radians) the barrels drop to when the action is broken open.
+const BREAK_ANGLE: f32 = 0.6;
+
+/// How long (in seconds) it takes to break the action open or snap it closed.
+const BREAK_TIME: f32 = 0.25;
+
+/// The speed (in meters per second) at which the ejectors throw shells clear of the chambers.
+const EJECT_SPEED: f32 = 3.0;
+
+/// One of the shotgun's two barrels.
+#[derive(Debug)]
+struct Barrel {
+    chamber: Option<Cartridge>,
+
+    /// Whether the barrel's hammer is cocked. Both hammers are cocked by opening the action.
+    cocked: bool,
+
+    bore: Bore,
+
+    /// The chamber's position relative to the hinge, used to position the loaded shell.
+    chamber_offset: Vector3,
+}
+
+/// A double-barrel, break-action shotgun.
+///
+/// Each barrel has its own trigger: the front trigger fires the right barrel and the rear trigger
+/// fires the left. Opening the action cocks both barrels and lifts the shells out of the chambers
+/// so that they can be ejected or replaced.
+#[derive(Debug)]
+pub struct Shotgun {
+    pub transform: Transform,
+    pub mesh_renderer: MeshRenderer,
+    pub rigidbody: Rigidbody,
+    pub recoil: Recoil,
+
+    /// The chamberings of the cartridges the gun can be loaded with.
+    pub chamberings: Vec<String>,
+
+    pub barrels_transform: Transform,
+    pub barrels_renderer: MeshRenderer,
+
+    barrels: [Barrel; 2],
+
+    /// The point (relative to the gun) the barrels pivot around when the action is opened.
+    hinge_offset: Vector3,
+    barrels_offset: Vector3,
+    action_open: bool,
+    break_angle: f32,
+    break_tween: Option<AngleTween>,
+
+    /// Whether the rear trigger was held last frame, so that holding it only fires once.
+    rear_trigger_held: bool,
+
+    events: Vec<ActionEvent>,
+
+    bullets: Arc<Mutex<BulletPool>>,
+}
+
+impl Shotgun {
+    pub fn new(
+        mesh: &Mesh,
+        barrels_mesh: &Mesh,
+        bullets: Arc<Mutex<BulletPool>>,
+        start_pos: Point,
+        start_orientation: Orientation,
+    ) -> Shotgun {
+        let mut transform = Transform::new();
+        transform.set_position(start_pos);
+        transform.set_orientation(start_orientation);
+        let mesh_renderer = MeshRenderer::new(&mesh, &transform);
+        let mut rigidbody = Rigidbody::new();
+        rigidbody.integrator = Integrator::VelocityVerlet;
+
+        // Approximate the gun as a long solid box, most of the mass is in the barrels.
+        rigidbody.mass = 3.2;
+        rigidbody.inertia = InertiaTensor::from_shape(
+            &Shape::Obb { half_extents: Vector3::new(0.03, 0.05, 0.35) },
+            rigidbody.mass,
+        );
+        rigidbody.teleport(start_pos, start_orientation);
+
+        let mut barrels_transform = Transform::new();
+        barrels_transform.set_position(start_pos);
+        barrels_transform.set_scale(Vector3::new(0.04, 0.02, 0.7));
+        let barrels_renderer = MeshRenderer::new(&barrels_mesh, &barrels_transform);
+
+        // The number of pellets fired by a shot shell is set by the shell, but how widely they
+        // spread is set by the barrel's choke.
+        let barrel = |x: f32| {
+            let mut bore = Bore::new(Vector3::new(x, 0.05, -0.7));
+            bore.spread = 0.03;
+
+            Barrel {
+                chamber: None,
+                cocked: false,
+                bore: bore,
+                chamber_offset: Vector3::new(x, 0.0, 0.0),
+            }
+        };
+
+        Shotgun {
+            transform: transform,
+            mesh_renderer: mesh_renderer,
+            rigidbody: rigidbody,
+            recoil: Recoil {
+                bore_offset: Vector3::new(0.0, 0.05, -0.35),
+                .. Recoil::default()
+            },
+
+            chamberings: vec!["12_gauge".into()],
+
+            barrels_transform: barrels_transform,
+            barrels_renderer: barrels_renderer,
+
+            barrels: [barrel(0.01), barrel(-0.01)],
+
+            hinge_offset: Vector3::new(0.0, 0.03, -0.05),
+            barrels_offset: Vector3::new(0.0, 0.05, -0.35),
+            action_open: false,
+            break_angle: 0.0,
+            break_tween: None,
+
+            rear_trigger_held: false,
+
+            events: Vec::new(),
+
+            bullets: bullets,
+        }
+    }
+
+    /// Applies any shotgun settings found in `config`, leaving the others unchanged.
+    pub fn apply_config(&mut self, config: &Config) {
+        for barrel in &mut self.barrels {
+            barrel.bore.spread = config.get_or("spread", barrel.bore.spread);
+        }
+    }
+
+    /// Reseeds each barrel's bore, giving every barrel its own sequence. See `Bore::seed()`.
+    pub fn seed(&mut self, seed: u32) {
+        for (index, barrel) in self.barrels.iter_mut().enumerate() {
+            barrel.bore.seed(seed.wrapping_add(index as u32));
+        }
+    }
+
+    /// Takes all action events that have happened since the last call.
+    pub fn drain_events(&mut self) -> Vec<ActionEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Pulls the front trigger, firing the right barrel.
+    pub fn pull_front_trigger(&mut self) {
+        self.fire_barrel(0);
+    }
+
+    /// Pulls the rear trigger, firing the left barrel.
+    pub fn pull_rear_trigger(&mut self) {
+        self.fire_barrel(1);
+    }
+
+    pub fn is_action_open(&self) -> bool {
+        self.action_open
+    }
+
+    /// Breaks the action open, cocking both hammers.
+    pub fn open_action(&mut self) {
+        if !self.action_open {
+            self.action_open = true;
+            self.break_tween = Some(AngleTween::new(self.break_angle, BREAK_ANGLE, BREAK_TIME, tween::ease_out_quad));
+            self.events.push(ActionEvent::ActionOpened);
+
+            if self.barrels.iter().any(|barrel| !barrel.cocked) {
+                for barrel in &mut self.barrels {
+                    barrel.cocked = true;
+                }
+                self.events.push(ActionEvent::Cocked);
+            }
+        }
+    }
+
+    /// Snaps the action closed.
+    pub fn close_action(&mut self) {
+        if self.action_open {
+            self.action_open = false;
+            self.break_tween = Some(AngleTween::new(self.break_angle, 0.0, BREAK_TIME, tween::ease_in_quad));
+            self.events.push(ActionEvent::ActionClosed);
+        }
+    }
+
+    /// Loads a shell into the first empty chamber.
+    ///
+    /// Fails if both chambers are full or the action is closed.
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        if !cartridge.spec.fits(&self.chamberings) {
+            return Err(cartridge);
+        }
+
+        if !self.action_open {
+            return Err(cartridge);
+        }
+
+        // TODO: Animate loading the shell.
+        match self.barrels.iter_mut().find(|barrel| barrel.chamber.is_none()) {
+            Some(barrel) => {
+                barrel.chamber = Some(cartridge);
+                Ok(())
+            },
+            None => Err(cartridge),
+        }
+    }
+
+    /// Throws every shell out of the chambers.
+    ///
+    /// Returns nothing if the action is closed.
+    pub fn eject_cartridges(&mut self) -> Vec<Cartridge> {
+        if !self.action_open {
+            return Vec::new();
+        }
+
+        let mut cartridges = Vec::new();
+        for barrel in &mut self.barrels {
+            if let Some(mut shell) = barrel.chamber.take() {
+                barrel.bore.extract(&mut shell);
+                cartridges.push(shell);
+            }
+        }
+        self.events.push(ActionEvent::CartridgesEjected(cartridges.len()));
+        cartridges
+    }
+
+    /// Pulls a single shell out by hand, starting with the right barrel.
+    ///
+    /// Returns `None` if both chambers are empty or the action is closed.
+    pub fn pull_cartridge(&mut self) -> Option<Cartridge> {
+        if !self.action_open {
+            return None;
+        }
+
+        for barrel in &mut self.barrels {
+            if let Some(mut shell) = barrel.chamber.take() {
+                barrel.bore.extract(&mut shell);
+                return Some(shell);
+            }
+        }
+
+        None
+    }
+
+    /// Gets the speed and direction shells are thrown when ejected, in world space.
+    pub fn eject_velocity(&self) -> Vector3 {
+        // The ejectors throw the shells back and up out of the tilted chambers.
+        let direction = Vector3::new(0.0, 0.5, 1.0).normalized();
+        self.rigidbody.velocity() + self.transform.orientation() * (direction * EJECT_SPEED)
+    }
+
+    /// Drops the hammer for `index`, firing a spread of pellets if its chamber holds a live
+    /// shell.
+    fn fire_barrel(&mut self, index: usize) {
+        // The barrels don't line up with the firing pins while the action is open, and a hammer
+        // that's already fallen needs the action opened again to re-cock it. A barrel that's
+        // hanging fire can't be struck again until its shell goes off or is pulled.
+        let ready = self.barrels[index].cocked && !self.barrels[index].bore.is_hanging_fire();
+        if self.action_open || !ready {
+            self.events.push(ActionEvent::TriggerBlocked);
+            return;
+        }
+
+        // TODO: Play `audio/Shotgun_Blast-Jim_Rogers-1914772763.wav` on gunshot once the engine
+        // exposes audio sources again; the audio manager the other guns used is gone.
+        let barrel = &mut self.barrels[index];
+        barrel.cocked = false;
+        let event = match barrel.chamber.as_mut() {
+            Some(shell) => {
+                let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                barrel.bore.strike(shell, &mut firing)
+            },
+            None => ActionEvent::DryFired,
+        };
+        self.events.push(event);
+    }
+
+    /// Counts down pending hang-fires, firing each shell that's still chambered once it goes off.
+    ///
+    /// A shell that goes off with the action open isn't lined up with its barrel, so it burns
+    /// out harmlessly in the open breech.
+    pub fn update(&mut self, delta: f32) {
+        for barrel in &mut self.barrels {
+            if barrel.bore.update(delta) {
+                if let Some(shell) = barrel.chamber.as_mut() {
+                    if self.action_open {
+                        shell.fire();
+                        continue;
+                    }
+
+                    let mut firing = Firing::new(&self.bullets, &self.transform, &mut self.rigidbody, &self.recoil);
+                    let event = barrel.bore.discharge(shell, &mut firing);
+                    self.events.push(event);
+                }
+            }
+        }
+    }
+
+    /// Pushes a rod down both barrels, knocking out anything stuck in them.
+    ///
+    /// The barrels can only be reached with the action broken open.
+    pub fn clear_bore(&mut self) {
+        if !self.action_open {
+            return;
+        }
+
+        let mut cleared = false;
+        for barrel in &mut self.barrels {
+            cleared |= barrel.bore.clear();
+        }
+
+        if cleared {
+            self.events.push(ActionEvent::BoreCleared);
+        }
+    }
+
+    pub fn update_transforms(&mut self) {
+        if let Some(mut tween) = self.break_tween {
+            match tween.advance(time::delta_f32()) {
+                Some(angle) => {
+                    self.break_angle = angle;
+                    self.break_tween = Some(tween);
+                },
+                None => {
+                    self.break_angle = tween.end_angle();
+                    self.break_tween = None;
+                },
+            }
+        }
+
+        // Drop the barrels down around the hinge, which runs across the gun below the chambers.
+        let break_rotation = Orientation::from_eulers(-self.break_angle, 0.0, 0.0);
+        let local_position = self.hinge_offset + break_rotation * (self.barrels_offset - self.hinge_offset);
+        let barrels_orientation = self.transform.orientation() + break_rotation;
+        self.barrels_transform.set_position(self.transform.position() + self.transform.orientation() * local_position);
+        self.barrels_transform.set_orientation(barrels_orientation);
+
+        // Shells sit in the chambers and tip down with the barrels.
+        let hinge = self.transform.position() + self.transform.orientation() * self.hinge_offset;
+        for barrel in &mut self.barrels {
+            if let Some(shell) = barrel.chamber.as_mut() {
+                let offset = barrels_orientation * barrel.chamber_offset;
+                shell.transform.set_position(hinge + offset);
+                shell.transform.set_orientation(barrels_orientation);
+            }
+        }
+    }
+}
+
+impl Weapon for Shotgun {
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn rigidbody(&self) -> &Rigidbody {
+        &self.rigidbody
+    }
+
+    fn rigidbody_mut(&mut self) -> &mut Rigidbody {
+        &mut self.rigidbody
+    }
+
+    fn recoil(&self) -> &Recoil {
+        &self.recoil
+    }
+
+    fn pull_trigger(&mut self) {
+        self.pull_front_trigger();
+    }
+
+    /// The secondary button pulls the rear trigger.
+    fn secondary_action(&mut self, held: bool) {
+        if held && !self.rear_trigger_held {
+            self.pull_rear_trigger();
+        }
+
+        self.rear_trigger_held = held;
+    }
+
+    fn is_action_open(&self) -> bool {
+        Shotgun::is_action_open(self)
+    }
+
+    fn open_action(&mut self) {
+        Shotgun::open_action(self);
+    }
+
+    fn close_action(&mut self) {
+        Shotgun::close_action(self);
+    }
+
+    /// There's nothing to cycle on a break-action.
+    fn accepts(&self, spec: &CartridgeSpec) -> bool {
+        spec.fits(&self.chamberings)
+    }
+
+    fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge> {
+        Shotgun::load_cartridge(self, cartridge)
+    }
+
+    fn unload(&mut self) -> Vec<Cartridge> {
+        self.eject_cartridges()
+    }
+
+    fn unload_one(&mut self) -> Option<Cartridge> {
+        self.pull_cartridge()
+    }
+
+    fn eject_velocity(&self) -> Vector3 {
+        Shotgun::eject_velocity(self)
+    }
+
+    fn clear_bore(&mut self) {
+        Shotgun::clear_bore(self);
+    }
+
+    fn update(&mut self, delta: f32) {
+        Shotgun::update(self, delta);
+    }
+
+    fn drain_events(&mut self) -> Vec<ActionEvent> {
+        Shotgun::drain_events(self)
+    }
+
+    fn interpolate(&mut self, alpha: f32) {
+        self.rigidbody.interpolate(&mut self.transform, alpha);
+    }
+
+    fn update_transforms(&mut self) {
+        Shotgun::update_transforms(self);
+    }
+}
@@ -22,3 +22,56 @@ pub fn ease_out_back(t: f32) -> f32 {
     let f = 1.0 - t;
     return 1.0 - (f * f * f - f * f32::sin(f * PI));
 }
+
+/// Eases in, starting slow and accelerating.
+///
+/// Start: 0, end: 1, Input range: [0, 1]
+pub fn ease_in_quad(t: f32) -> f32 {
+    t * t
+}
+
+/// Eases out, starting fast and decelerating.
+///
+/// Start: 0, end: 1, Input range: [0, 1]
+pub fn ease_out_quad(t: f32) -> f32 {
+    t * (2.0 - t)
+}
+
+/// Eases an angle between two values over time.
+#[derive(Debug, Clone, Copy)]
+pub struct AngleTween {
+    time: f32,
+    target_time: f32,
+    start_angle: f32,
+    end_angle: f32,
+    easing: fn(f32) -> f32,
+}
+
+impl AngleTween {
+    pub fn new(start_angle: f32, end_angle: f32, target_time: f32, easing: fn(f32) -> f32) -> AngleTween {
+        AngleTween {
+            time: 0.0,
+            target_time: target_time,
+            start_angle: start_angle,
+            end_angle: end_angle,
+            easing: easing,
+        }
+    }
+
+    /// Gets the angle the tween finishes at.
+    pub fn end_angle(&self) -> f32 {
+        self.end_angle
+    }
+
+    /// Advances the tween, returning the current angle or `None` once the tween is done.
+    pub fn advance(&mut self, delta: f32) -> Option<f32> {
+        self.time += delta;
+
+        if self.time > self.target_time {
+            None
+        } else {
+            let t = (self.easing)(self.time / self.target_time);
+            Some(self.start_angle + (self.end_angle - self.start_angle) * t)
+        }
+    }
+}
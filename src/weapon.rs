@@ -0,0 +1,195 @@
+use cartridge::CartridgeSpec;
+use gun::Cartridge;
+use gunship::math::*;
+use gunship::transform::Transform;
+use physics::Rigidbody;
+use recoil::{Load, Recoil};
+use std::fmt::Debug;
+
+/// Notifies animation, audio, and UI of changes to the state of a weapon's action.
+#[derive(Debug, Clone, Copy)]
+pub enum ActionEvent {
+    /// The hammer was drawn back to half-cock.
+    HalfCocked,
+
+    /// The hammer was drawn back to full cock, either by hand or by a double-action trigger pull.
+    Cocked,
+
+    /// The hammer was lowered without firing.
+    Decocked,
+
+    /// The trigger was pulled but the action wouldn't let it fire, e.g. at half-cock.
+    TriggerBlocked,
+
+    /// The hammer fell on a live cartridge and the gun fired.
+    Fired(Load),
+
+    /// The hammer fell on an empty chamber or a spent cartridge.
+    DryFired,
+
+    /// The hammer fell on a live cartridge but it didn't go off.
+    Misfired,
+
+    /// The hammer fell on a live cartridge that's slow to go off. It'll fire shortly.
+    HangFire,
+
+    /// The cartridge only had enough power to push its bullet partway down the barrel, where it's
+    /// now stuck.
+    Squibbed,
+
+    /// The gun fired into a bullet stuck in the barrel, so nothing came out.
+    BoreObstructed,
+
+    /// A stuck bullet was knocked out of the barrel.
+    BoreCleared,
+
+    /// The action was opened for loading, e.g. the revolver's cylinder was swung out.
+    ActionOpened,
+
+    /// The action was closed again.
+    ActionClosed,
+
+    /// The specified number of cartridges were thrown clear of the gun.
+    CartridgesEjected(usize),
+
+    /// The slide was pulled back and released by hand.
+    SlideRacked,
+
+    /// The slide locked back on an empty magazine.
+    SlideLocked,
+
+    /// The slide was released from lock and ran forward.
+    SlideReleased,
+
+    /// The magazine was pulled out of the gun.
+    MagazineRemoved,
+
+    /// The magazine was seated in the gun.
+    MagazineInserted,
+
+    /// The manual safety was put on.
+    SafetyEngaged,
+
+    /// The manual safety was taken off.
+    SafetyDisengaged,
+}
+
+/// A firearm the player can wield.
+///
+/// The player's controls map directly onto these methods, and it's up to each weapon to decide
+/// what they mean for its action. `release_trigger`, `secondary_action`, `safety`, `decock`, and
+/// `cycle` default to doing nothing, so weapons without a matching control can leave them out.
+pub trait Weapon: Debug {
+    fn transform(&self) -> &Transform;
+    fn rigidbody(&self) -> &Rigidbody;
+    fn rigidbody_mut(&mut self) -> &mut Rigidbody;
+    fn recoil(&self) -> &Recoil;
+
+    /// Pulls the trigger.
+    fn pull_trigger(&mut self);
+
+    /// Lets go of the trigger.
+    ///
+    /// Called every frame the player isn't holding the trigger.
+    fn release_trigger(&mut self) {}
+
+    /// Performs the weapon's secondary action, e.g. thumbing back the hammer.
+    ///
+    /// Called every frame with whether or not the player is holding the secondary button.
+    fn secondary_action(&mut self, _held: bool) {}
+
+    /// Puts the weapon on safe, e.g. at half-cock.
+    fn safety(&mut self) {}
+
+    /// Lowers a cocked hammer or striker without firing.
+    fn decock(&mut self) {}
+
+    fn is_action_open(&self) -> bool;
+
+    /// Opens the action so that the weapon can be loaded and unloaded.
+    fn open_action(&mut self);
+
+    /// Closes the action, readying the weapon to fire.
+    fn close_action(&mut self);
+
+    /// Works the part of the action that cartridges are loaded through, e.g. rotating a
+    /// revolver's cylinder by `amount` chambers.
+    fn cycle(&mut self, _amount: isize) {}
+
+    /// Checks if the weapon is chambered for cartridges of the specified type.
+    fn accepts(&self, spec: &CartridgeSpec) -> bool;
+
+    /// Loads a single cartridge, or gives it back if there's nowhere to put it or it doesn't fit
+    /// the weapon.
+    fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<(), Cartridge>;
+
+    /// Loads a clip of cartridges, returning any that didn't fit.
+    ///
+    /// By default the cartridges are loaded one at a time until one is rejected.
+    fn load_clip(&mut self, cartridges: Vec<Cartridge>) -> Vec<Cartridge> {
+        let mut remaining = Vec::new();
+        for cartridge in cartridges {
+            if !remaining.is_empty() {
+                remaining.push(cartridge);
+            } else if let Err(cartridge) = self.load_cartridge(cartridge) {
+                remaining.push(cartridge);
+            }
+        }
+
+        remaining
+    }
+
+    /// Removes every cartridge from the weapon at once.
+    fn unload(&mut self) -> Vec<Cartridge>;
+
+    /// Removes a single cartridge by hand.
+    fn unload_one(&mut self) -> Option<Cartridge>;
+
+    /// Gets the speed and direction cartridges are thrown when ejected, in world space.
+    fn eject_velocity(&self) -> Vector3;
+
+    /// Knocks a stuck bullet out of the barrel. Only possible while the action is open.
+    fn clear_bore(&mut self);
+
+    /// Advances anything in the action that plays out over time, such as a hang-fire waiting to
+    /// go off.
+    fn update(&mut self, delta: f32);
+
+    /// Takes all action events that have happened since the last call.
+    fn drain_events(&mut self) -> Vec<ActionEvent>;
+
+    /// Takes every cartridge the action has thrown out on its own since the last call, e.g. the
+    /// spent cases from a semi-automatic.
+    fn drain_ejected(&mut self) -> Vec<Cartridge> {
+        Vec::new()
+    }
+
+    /// Gets how far the weapon has been raised to the player's eye, from 0 at the hip to 1 when
+    /// fully aimed down the sights.
+    fn aim(&self) -> f32 {
+        0.0
+    }
+
+    /// Brings the weapon up close to look it over, or lowers it again.
+    ///
+    /// Called every frame with whether or not the player is holding the inspect button.
+    fn inspect(&mut self, _inspecting: bool) {}
+
+    /// Gets how far the weapon has been brought up for inspection, from 0 in the hand to 1 right
+    /// in front of the player's face.
+    fn inspection(&self) -> f32 {
+        0.0
+    }
+
+    /// Gets how much the weapon's sights are currently magnifying the player's view.
+    fn magnification(&self) -> f32 {
+        1.0
+    }
+
+    /// Updates the rendered transform to lie `alpha` of the way between the last two physics
+    /// steps.
+    fn interpolate(&mut self, alpha: f32);
+
+    /// Animates the moving parts of the weapon for the frame.
+    fn update_transforms(&mut self);
+}